@@ -0,0 +1,302 @@
+use std::collections::BTreeSet;
+use std::marker::PhantomData;
+
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::actions::{Action, Actions};
+use crate::audio::{AudioRequest, SoundEffect};
+use crate::book::RecipeBook;
+use crate::common::*;
+use crate::customers::{Archetype, Roster};
+use crate::ending::{Ending, Unlocks};
+use crate::events::CustomerReacted;
+use crate::layout::{Anchored, DESIGN_HEIGHT, DESIGN_WIDTH};
+use crate::locale::{Locale, Localized};
+use crate::storage;
+
+const ROW_HEIGHT: f32 = 52.;
+const TOAST_SECONDS: f32 = 4.;
+const TOAST_FADE: f32 = 0.5;
+const TOAST_HEIGHT: f32 = 36.;
+const UNLOCKED: Color = Color::rgb(1., 0.85, 0.3);
+const LOCKED: Color = Color::rgb(0.45, 0.45, 0.45);
+
+// What unlocks an achievement, counted over every run.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub enum Condition {
+    // At least this many dishes in the recipe book.
+    Discover(usize),
+    // This many delighted customers in total.
+    Delight(u32),
+    // Every regular delighted at least once.
+    DelightEveryone,
+    Outcome { customer: String, outcome: String },
+    Ending(Ending),
+    // This many different endings seen.
+    Endings(usize)
+}
+
+impl Condition {
+    pub fn is_met(&self
+        , record: &AchievementRecord
+        , book: &RecipeBook
+        , unlocks: &Unlocks
+        , archetypes: &[Archetype]) -> bool
+    {
+        match self {
+            Condition::Discover(dishes) => book.0.len() >= *dishes,
+            Condition::Delight(customers) => record.delights >= *customers,
+            // Nobody to delight until the customers have loaded.
+            Condition::DelightEveryone => archetypes.iter().any(|archetype| archetype.frequency > 0.)
+                && archetypes.iter()
+                    .filter(|archetype| archetype.frequency > 0.)
+                    .all(|archetype| record.delighted.contains(&archetype.id)),
+            Condition::Outcome { customer, outcome } => record.outcomes.contains(&(customer.clone(), outcome.clone())),
+            Condition::Ending(ending) => unlocks.endings.contains(ending),
+            Condition::Endings(endings) => unlocks.endings.len() >= *endings
+        }
+    }
+}
+
+// `title` and `description` are string table keys.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Achievement {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub condition: Condition
+}
+
+// Every achievement, loaded from `game.achievements.ron`.
+#[derive(Debug, Clone, Default, TypeUuid)]
+#[uuid = "8d2c4a71-5e3f-4b96-a0c8-7f1e9b3d2a64"]
+pub struct Achievements(pub Vec<Achievement>);
+
+#[derive(Default)]
+pub struct AchievementsLoader;
+
+impl AssetLoader for AchievementsLoader {
+    fn load<'a>(&'a self
+        , bytes: &'a [u8]
+        , load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), anyhow::Error>>
+    {
+        Box::pin(async move {
+            let achievements: Vec<Achievement> = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(Achievements(achievements)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["achievements.ron"]
+    }
+}
+
+pub struct AchievementsHandle(pub Handle<Achievements>);
+
+impl AchievementsHandle {
+    pub fn load(asset_server: &AssetServer) -> Self {
+        AchievementsHandle(asset_server.load("game.achievements.ron"))
+    }
+}
+
+#[derive(SystemParam)]
+pub struct AchievementList<'w, 's> {
+    handle: Res<'w, AchievementsHandle>,
+    achievements: Res<'w, Assets<Achievements>>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>
+}
+
+impl<'w, 's> AchievementList<'w, 's> {
+    // Empty until the achievements have loaded.
+    pub fn achievements(&self) -> &[Achievement] {
+        self.achievements.get(&self.handle.0)
+            .map(|Achievements(achievements)| achievements.as_slice())
+            .unwrap_or(&[])
+    }
+}
+
+// The achievements unlocked so far and the tallies the recipe book and the
+// unlocks don't already keep. Kept apart from the save and the unlocks so
+// neither starting over nor finishing a run touches it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AchievementRecord {
+    pub unlocked: BTreeSet<String>,
+    pub delights: u32,
+    pub delighted: BTreeSet<String>,
+    pub outcomes: BTreeSet<(String, String)>
+}
+
+impl AchievementRecord {
+    pub fn load() -> Self {
+        storage::load("achievements")
+    }
+
+    pub fn record(&mut self, reaction: &CustomerReacted) {
+        if reaction.delighted {
+            self.delights += 1;
+            self.delighted.insert(reaction.customer.clone());
+        }
+        if let Some(outcome) = &reaction.outcome {
+            self.outcomes.insert((reaction.customer.clone(), outcome.clone()));
+        }
+    }
+
+    // Unlocks whatever the tallies now allow, returning what was new.
+    pub fn unlock<'a>(&mut self
+        , achievements: &'a [Achievement]
+        , book: &RecipeBook
+        , unlocks: &Unlocks
+        , archetypes: &[Archetype]) -> Vec<&'a Achievement>
+    {
+        let unlocked: Vec<&Achievement> = achievements.iter()
+            .filter(|achievement| !self.unlocked.contains(&achievement.id))
+            .filter(|achievement| achievement.condition.is_met(self, book, unlocks, archetypes))
+            .collect();
+        for achievement in &unlocked {
+            self.unlocked.insert(achievement.id.clone());
+        }
+        unlocked
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn track_achievements(mut commands: Commands
+    , mut reactions: EventReader<CustomerReacted>
+    , mut loaded: EventReader<AssetEvent<Achievements>>
+    , mut record: ResMut<AchievementRecord>
+    , list: AchievementList
+    , book: Res<RecipeBook>
+    , unlocks: Res<Unlocks>
+    , roster: Roster
+    , toasts: Query<&Toast>
+    , mut sounds: EventWriter<AudioRequest>
+    , locale: Locale
+    , fonts: Res<Fonts>)
+{
+    let mut changed = book.is_changed() || unlocks.is_changed() || loaded.iter().count() > 0;
+    for reaction in reactions.iter() {
+        record.record(reaction);
+        changed = true;
+    }
+    if !changed {
+        return;
+    }
+
+    let showing = toasts.iter().count();
+    let unlocked = record.unlock(list.achievements(), &book, &unlocks, roster.archetypes());
+    for (index, achievement) in unlocked.iter().enumerate() {
+        info!("Unlocked achievement {}", achievement.id);
+        let toast = Localized::new("achievements.toast", 20.)
+            .with_arg("title", locale.get(&achievement.title));
+        let (text, effects) = toast.text(&locale, &fonts);
+        // Stacked under any still showing, and above the transition overlay
+        // so scene changes don't hide them.
+        let y = -TOAST_HEIGHT - (showing + index) as f32*TOAST_HEIGHT;
+        commands.spawn_bundle(Text2dBundle {
+            text: text.with_alignment(TextAlignment::CENTER),
+            ..default()
+        }).insert(Toast(Timer::from_seconds(TOAST_SECONDS, false)))
+            .insert(Anchored::new(Vec2::new(0., 0.5), 0., y, 60.))
+            .insert(toast)
+            .insert(effects);
+    }
+    if !unlocked.is_empty() {
+        sounds.send(AudioRequest::Effect(SoundEffect::Success));
+    }
+    storage::store("achievements", &*record);
+}
+
+pub fn toast_fade(mut commands: Commands
+    , mut toasts: Query<(Entity, &mut Toast, &mut Localized)>
+    , time: Res<Time>)
+{
+    for (entity, mut toast, mut localized) in toasts.iter_mut() {
+        let Toast(timer) = &mut *toast;
+        if timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let left = timer.duration().as_secs_f32() - timer.elapsed_secs();
+        if left < TOAST_FADE {
+            localized.color.set_a(left/TOAST_FADE);
+        }
+    }
+}
+
+pub fn achievements_setup(mut commands: Commands
+    , record: Res<AchievementRecord>
+    , list: AchievementList
+    , locale: Locale
+    , fonts: Res<Fonts>)
+{
+    commands.spawn_bundle(SpriteBundle {
+        sprite: Sprite {
+            color: Color::rgba(0.1, 0.05, 0.1, 0.92),
+            custom_size: Some((DESIGN_WIDTH, DESIGN_HEIGHT).into()),
+            ..default()
+        },
+        ..default()
+    }).insert(AchievementsScene)
+        .insert(Anchored::center(0., 0., 10.).covering());
+
+    let achievements = list.achievements();
+    let unlocked = achievements.iter()
+        .filter(|achievement| record.unlocked.contains(&achievement.id))
+        .count();
+    let top = DESIGN_HEIGHT/2. - 40.;
+    let mut lines = vec![
+        (Localized::new("achievements.title", 28.), top),
+        (Localized::new("achievements.count", 16.)
+            .with_arg("unlocked", unlocked)
+            .with_arg("total", achievements.len()), top - 34.)
+    ];
+    for (index, achievement) in achievements.iter().enumerate() {
+        let color = if record.unlocked.contains(&achievement.id) { UNLOCKED } else { LOCKED };
+        let y = top - 84. - index as f32*ROW_HEIGHT;
+        let mut title = Localized::new(achievement.title.clone(), 20.);
+        title.color = color;
+        let mut description = Localized::new(achievement.description.clone(), 14.);
+        description.color = color;
+        lines.push((title, y));
+        lines.push((description, y - 20.));
+    }
+
+    for (line, y) in lines {
+        let (text, effects) = line.text(&locale, &fonts);
+        commands.spawn_bundle(Text2dBundle {
+            text: text.with_alignment(TextAlignment::CENTER),
+            ..default()
+        }).insert(AchievementsScene)
+            .insert(Anchored::center(0., y, 11.))
+            .insert(line)
+            .insert(effects);
+    }
+}
+
+pub fn achievements_input(mut actions: ResMut<Actions>, mut game_state: ResMut<State<GameState>>) {
+    if actions.take(Action::Back) || actions.take(Action::Confirm) {
+        game_state.pop().ok();
+    }
+    actions.clear();
+}
+
+pub fn achievements_cleanup(mut commands: Commands, entities: Query<(Entity, &AchievementsScene)>) {
+    for (entity, _) in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[derive(Component)]
+pub struct AchievementsScene;
+
+// An unlock notice that fades away on its own.
+#[derive(Component)]
+pub struct Toast(pub Timer);
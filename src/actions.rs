@@ -0,0 +1,217 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::input::gamepad::GamepadEvent;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::gamepad::GamepadInput;
+use crate::storage;
+use crate::transition::Transition;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Action {
+    SelectIngredient(u32),
+    Confirm,
+    Advance,
+    Back,
+    Pause,
+    ToggleAuto,
+    ToggleSkip,
+    SwitchLanguage,
+    ToggleFullscreen,
+    Up,
+    Down,
+    Left,
+    Right,
+    Select
+}
+
+impl Action {
+    // Every action in the order the controls screen lists them.
+    pub const ALL: [Action; 19] = [
+        Action::Advance,
+        Action::Confirm,
+        Action::Back,
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::Select,
+        Action::SelectIngredient(1),
+        Action::SelectIngredient(2),
+        Action::SelectIngredient(3),
+        Action::SelectIngredient(4),
+        Action::SelectIngredient(5),
+        Action::SelectIngredient(6),
+        Action::Pause,
+        Action::ToggleAuto,
+        Action::ToggleSkip,
+        Action::SwitchLanguage,
+        Action::ToggleFullscreen
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Action::SelectIngredient(_) => "action.select_ingredient",
+            Action::Confirm => "action.confirm",
+            Action::Advance => "action.advance",
+            Action::Back => "action.back",
+            Action::Pause => "action.pause",
+            Action::ToggleAuto => "action.toggle_auto",
+            Action::ToggleSkip => "action.toggle_skip",
+            Action::SwitchLanguage => "action.switch_language",
+            Action::ToggleFullscreen => "action.toggle_fullscreen",
+            Action::Up => "action.up",
+            Action::Down => "action.down",
+            Action::Left => "action.left",
+            Action::Right => "action.right",
+            Action::Select => "action.select"
+        }
+    }
+
+    fn default_keys(&self) -> Vec<KeyCode> {
+        match self {
+            Action::SelectIngredient(1) => vec![KeyCode::Key1, KeyCode::Numpad1],
+            Action::SelectIngredient(2) => vec![KeyCode::Key2, KeyCode::Numpad2],
+            Action::SelectIngredient(3) => vec![KeyCode::Key3, KeyCode::Numpad3],
+            Action::SelectIngredient(4) => vec![KeyCode::Key4, KeyCode::Numpad4],
+            Action::SelectIngredient(5) => vec![KeyCode::Key5, KeyCode::Numpad5],
+            Action::SelectIngredient(6) => vec![KeyCode::Key6, KeyCode::Numpad6],
+            Action::SelectIngredient(_) => vec![],
+            Action::Confirm => vec![KeyCode::Return, KeyCode::NumpadEnter],
+            Action::Advance => vec![KeyCode::Space, KeyCode::Return, KeyCode::NumpadEnter],
+            Action::Back => vec![KeyCode::Back],
+            Action::Pause => vec![KeyCode::Escape, KeyCode::P],
+            Action::ToggleAuto => vec![KeyCode::Q],
+            Action::ToggleSkip => vec![KeyCode::E],
+            Action::SwitchLanguage => vec![KeyCode::F2],
+            Action::ToggleFullscreen => vec![KeyCode::F11],
+            Action::Up => vec![KeyCode::Up, KeyCode::W],
+            Action::Down => vec![KeyCode::Down, KeyCode::S],
+            Action::Left => vec![KeyCode::Left, KeyCode::A],
+            Action::Right => vec![KeyCode::Right, KeyCode::D],
+            Action::Select => vec![KeyCode::Space]
+        }
+    }
+}
+
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ActionSystem;
+
+// Which keys trigger which action. Only the bindings that differ from the
+// defaults are saved, so new actions pick up their default keys.
+pub struct Bindings(HashMap<Action, Vec<KeyCode>>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings(Action::ALL.iter().map(|action| (*action, action.default_keys())).collect())
+    }
+}
+
+impl Bindings {
+    pub fn load() -> Self {
+        let mut bindings = Bindings::default();
+        let overrides: Vec<(Action, Vec<KeyCode>)> = storage::load("bindings");
+        for (action, keys) in overrides {
+            bindings.0.insert(action, keys);
+        }
+        bindings
+    }
+
+    fn save(&self) {
+        let Bindings(keys) = self;
+        let overrides: Vec<(Action, Vec<KeyCode>)> = Action::ALL.iter()
+            .filter(|action| keys.get(*action) != Some(&action.default_keys()))
+            .map(|action| (*action, self.keys(*action).to_vec()))
+            .collect();
+        storage::store("bindings", &overrides);
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        let Bindings(keys) = self;
+        keys.get(&action).map(|keys| keys.as_slice()).unwrap_or(&[])
+    }
+
+    // The keys for an action as they're shown on screen, or none if it's unbound.
+    pub fn describe(&self, action: Action) -> Option<String> {
+        let keys = self.keys(action);
+        if keys.is_empty() {
+            return None;
+        }
+        Some(keys.iter()
+            .map(|key| format!("{:?}", key))
+            .collect::<Vec<_>>()
+            .join(", "))
+    }
+
+    pub fn bind(&mut self, action: Action, key: KeyCode) {
+        self.0.insert(action, vec![key]);
+        self.save();
+    }
+
+    pub fn reset(&mut self) {
+        *self = Bindings::default();
+        self.save();
+    }
+}
+
+// The actions triggered this frame. Systems `take` the ones they handle, and a
+// system that changes the scene clears the rest so the same key press can't
+// also act on the scene that follows.
+#[derive(Debug, Clone, Default)]
+pub struct Actions(HashSet<Action>);
+
+impl Actions {
+    pub fn press(&mut self, action: Action) {
+        self.0.insert(action);
+    }
+
+    pub fn take(&mut self, action: Action) -> bool {
+        self.0.remove(&action)
+    }
+
+    pub fn take_selection(&mut self) -> Option<u32> {
+        let selected = self.0.iter()
+            .filter_map(|action| match action {
+                Action::SelectIngredient(n) => Some(*n),
+                _ => None
+            })
+            .min()?;
+        self.0.remove(&Action::SelectIngredient(selected));
+        Some(selected)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Action> + '_ {
+        self.0.iter().copied()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+pub fn collect_actions(keys: Res<Input<KeyCode>>
+    , mut gamepad_events: EventReader<GamepadEvent>
+    , mut gamepads: ResMut<GamepadInput>
+    , bindings: Res<Bindings>
+    , transition: Res<Transition>
+    , mut actions: ResMut<Actions>)
+{
+    actions.clear();
+    for key in keys.get_just_released() {
+        for action in Action::ALL {
+            if bindings.keys(action).contains(key) {
+                actions.press(action);
+            }
+        }
+    }
+    for event in gamepad_events.iter() {
+        for action in gamepads.handle(event) {
+            actions.press(action);
+        }
+    }
+    // Nothing responds while the scene is changing.
+    if transition.is_running() {
+        actions.clear();
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::customers::{Archetype, Part};
+
+// One visit of a customer's story. The dish served decides which chapter
+// they come back for next, and a chapter without somewhere to go is where
+// the story stays. Reactions fall back to the customer's usual pools.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Chapter {
+    pub id: String,
+    pub greeting: Vec<String>,
+    #[serde(default)]
+    pub delighted: Vec<String>,
+    #[serde(default)]
+    pub disappointed: Vec<String>,
+    #[serde(default)]
+    pub then_delighted: Option<String>,
+    #[serde(default)]
+    pub then_disappointed: Option<String>,
+    // Set on the chapters that close the story, naming how it turned out.
+    #[serde(default)]
+    pub outcome: Option<String>
+}
+
+impl Chapter {
+    pub fn lines(&self, part: Part) -> &[String] {
+        match part {
+            Part::Greeting => &self.greeting,
+            Part::Delighted => &self.delighted,
+            Part::Disappointed => &self.disappointed
+        }
+    }
+}
+
+// The chapter each customer with a story is on, by archetype id. Customers
+// who haven't been by yet start at the first chapter of their arc.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Arcs(pub BTreeMap<String, String>);
+
+impl Arcs {
+    pub fn chapter<'a>(&self, archetype: &'a Archetype) -> Option<&'a Chapter> {
+        let Arcs(chapters) = self;
+        match chapters.get(&archetype.id) {
+            Some(id) => archetype.chapter(id),
+            None => archetype.arc.first()
+        }
+    }
+
+    pub fn advance(&mut self, archetype: &Archetype, delighted: bool) {
+        let next = self.chapter(archetype).and_then(|chapter| {
+            if delighted { chapter.then_delighted.clone() } else { chapter.then_disappointed.clone() }
+        });
+        if let Some(next) = next {
+            self.0.insert(archetype.id.clone(), next);
+        }
+    }
+
+    pub fn outcome<'a>(&self, archetype: &'a Archetype) -> Option<&'a str> {
+        self.chapter(archetype).and_then(|chapter| chapter.outcome.as_deref())
+    }
+}
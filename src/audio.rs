@@ -0,0 +1,173 @@
+use bevy::audio::AudioSink;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::common::*;
+use crate::storage;
+
+// Seconds for one track to fade out while the next fades in.
+const CROSSFADE: f32 = 1.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundEffect {
+    Select,
+    Confirm,
+    Success,
+    Failure
+}
+
+impl SoundEffect {
+    pub fn path(&self) -> &'static str {
+        match self {
+            SoundEffect::Select => "sounds/select.wav",
+            SoundEffect::Confirm => "sounds/confirm.wav",
+            SoundEffect::Success => "sounds/success.wav",
+            SoundEffect::Failure => "sounds/failure.wav"
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    Music,
+    Effects
+}
+
+// Everything that wants a sound sends one of these, and only `play_audio` talks
+// to the audio device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioRequest {
+    Music(&'static str),
+    Effect(SoundEffect)
+}
+
+pub fn track(state: &GameState) -> Option<&'static str> {
+    match state {
+        GameState::Title | GameState::Dialogue | GameState::Intermission
+            | GameState::Ending | GameState::Credits => Some("music/dialogue.wav"),
+        GameState::Cooking => Some("music/cooking.wav"),
+        // Overlays keep whatever was playing underneath them.
+        GameState::Paused | GameState::Settings | GameState::Controls
+            | GameState::Achievements | GameState::Stats | GameState::Mods => None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Volume {
+    pub master: f32,
+    pub music: f32,
+    pub effects: f32
+}
+
+impl Default for Volume {
+    fn default() -> Self {
+        Volume {
+            master: 0.8,
+            music: 0.6,
+            effects: 0.8
+        }
+    }
+}
+
+impl Volume {
+    pub fn load() -> Self {
+        storage::load("volume")
+    }
+
+    pub fn save(&self) {
+        storage::store("volume", self);
+    }
+
+    pub fn of(&self, category: Category) -> f32 {
+        let volume = match category {
+            Category::Music => self.music,
+            Category::Effects => self.effects
+        };
+        (self.master*volume).clamp(0., 1.)
+    }
+}
+
+// A looping track and how far it has faded in, from 0 to 1.
+struct Playing {
+    track: &'static str,
+    sink: Handle<AudioSink>,
+    level: f32
+}
+
+#[derive(Default)]
+pub struct Music {
+    current: Option<Playing>,
+    fading: Vec<Playing>
+}
+
+impl Music {
+    pub fn track(&self) -> Option<&'static str> {
+        self.current.as_ref().map(|playing| playing.track)
+    }
+
+    fn tick(&mut self, delta: f32) {
+        let step = delta/CROSSFADE;
+        if let Some(current) = &mut self.current {
+            current.level = (current.level + step).min(1.);
+        }
+        for fading in &mut self.fading {
+            fading.level = (fading.level - step).max(0.);
+        }
+    }
+}
+
+pub fn state_music(game_state: Res<State<GameState>>, mut requests: EventWriter<AudioRequest>) {
+    if !game_state.is_changed() {
+        return;
+    }
+    if let Some(track) = track(game_state.current()) {
+        requests.send(AudioRequest::Music(track));
+    }
+}
+
+pub fn play_audio(mut requests: EventReader<AudioRequest>
+    , mut music: ResMut<Music>
+    , volume: Res<Volume>
+    , audio: Res<Audio>
+    , sinks: Res<Assets<AudioSink>>
+    , asset_server: Res<AssetServer>
+    , time: Res<Time>)
+{
+    for request in requests.iter() {
+        match *request {
+            AudioRequest::Music(track) => {
+                if music.track() == Some(track) {
+                    continue;
+                }
+                let sink = audio.play_with_settings(asset_server.load(track), PlaybackSettings::LOOP.with_volume(0.));
+                let next = Playing { track, sink: sinks.get_handle(sink), level: 0. };
+                if let Some(previous) = music.current.replace(next) {
+                    music.fading.push(previous);
+                }
+            }
+            AudioRequest::Effect(effect) => {
+                let settings = PlaybackSettings::ONCE.with_volume(volume.of(Category::Effects));
+                audio.play_with_settings(asset_server.load(effect.path()), settings);
+            }
+        }
+    }
+
+    music.tick(time.delta_seconds());
+    let level = volume.of(Category::Music);
+    let Music { current, fading } = &mut *music;
+    for playing in current.iter().chain(fading.iter()) {
+        if let Some(sink) = sinks.get(&playing.sink) {
+            sink.set_volume(playing.level*level);
+        }
+    }
+    // Sinks only exist once their track has loaded, so a track that faded out
+    // before that is kept until it can be stopped.
+    fading.retain(|playing| match sinks.get(&playing.sink) {
+        Some(sink) if playing.level <= 0. => {
+            sink.stop();
+            false
+        }
+        _ => true
+    });
+}
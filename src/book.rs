@@ -0,0 +1,37 @@
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::cooking::CookingSelection;
+use crate::events::DishCooked;
+use crate::recipes::{Recipe, RecipeList};
+use crate::storage;
+
+// Writes every dish into the book as it is cooked.
+pub fn record_recipe(mut cooked: EventReader<DishCooked>
+    , mut book: ResMut<RecipeBook>
+    , recipes: RecipeList)
+{
+    for dish in cooked.iter() {
+        book.discover(&dish.selection, recipes.recipes());
+    }
+}
+
+// Every successful combination the player has cooked, as (lower, higher) ingredient numbers.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecipeBook(pub BTreeSet<(u32, u32)>);
+
+impl RecipeBook {
+    pub fn load() -> Self {
+        storage::load("recipe_book")
+    }
+
+    pub fn discover(&mut self, selection: &CookingSelection, recipes: &[Recipe]) {
+        let CookingSelection(first, second) = selection;
+        let recipe = (*first.min(second), *first.max(second));
+        if selection.is_dish(recipes) && self.0.insert(recipe) {
+            storage::store("recipe_book", self);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::BTreeSet;
+use std::marker::PhantomData;
+
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::arcs::Arcs;
+use crate::campaign::DAYS;
+use crate::customers::{Archetype, Taste};
+use crate::reputation::Reputation;
+
+// Every run opens the shop on Monday the 27th of October, so the week ends
+// just after Halloween.
+const START_MONTH: u32 = 10;
+const START_DAY: u32 = 27;
+const MONTH_LENGTHS: [u32; 12] = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+const WEEKDAYS: [&str; 7] = ["weekday.monday", "weekday.tuesday", "weekday.wednesday", "weekday.thursday"
+    , "weekday.friday", "weekday.saturday", "weekday.sunday"];
+const MONTHS: [&str; 12] = ["month.january", "month.february", "month.march", "month.april", "month.may", "month.june"
+    , "month.july", "month.august", "month.september", "month.october", "month.november", "month.december"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Season {
+    Winter,
+    Spring,
+    Summer,
+    Autumn
+}
+
+impl Season {
+    pub fn key(&self) -> &'static str {
+        match self {
+            Season::Winter => "season.winter",
+            Season::Spring => "season.spring",
+            Season::Summer => "season.summer",
+            Season::Autumn => "season.autumn"
+        }
+    }
+}
+
+// What makes an event happen at the start of a day.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub enum Trigger {
+    // The nth day of the run, counting from one.
+    Day(u32),
+    Date { month: u32, day: u32 },
+    // A customer's story reached the outcome.
+    Outcome { customer: String, outcome: String },
+    // A faction thinks at least this well of the shop.
+    Reputation { faction: String, at_least: i32 }
+}
+
+// Something special that happens on one day of a run, at most once. Its
+// guests come in before anyone else, and while it lasts every order asks
+// for the `demand`ed flavour if there is one.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct StoryEvent {
+    pub id: String,
+    pub name: String,
+    pub when: Trigger,
+    #[serde(default)]
+    pub guests: Vec<String>,
+    #[serde(default)]
+    pub demand: Option<Taste>
+}
+
+// Every scheduled event, loaded from `story.events.ron`.
+#[derive(Debug, Clone, Default, TypeUuid)]
+#[uuid = "3b6f0e2d-9a41-4c7e-b5d8-61f2a0c9e4d7"]
+pub struct StoryEvents(pub Vec<StoryEvent>);
+
+#[derive(Default)]
+pub struct StoryEventsLoader;
+
+impl AssetLoader for StoryEventsLoader {
+    fn load<'a>(&'a self
+        , bytes: &'a [u8]
+        , load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), anyhow::Error>>
+    {
+        Box::pin(async move {
+            let events: Vec<StoryEvent> = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(StoryEvents(events)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["events.ron"]
+    }
+}
+
+pub struct StoryEventsHandle(pub Handle<StoryEvents>);
+
+impl StoryEventsHandle {
+    pub fn load(asset_server: &AssetServer) -> Self {
+        StoryEventsHandle(asset_server.load("story.events.ron"))
+    }
+}
+
+#[derive(SystemParam)]
+pub struct StorySchedule<'w, 's> {
+    handle: Res<'w, StoryEventsHandle>,
+    events: Res<'w, Assets<StoryEvents>>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>
+}
+
+impl<'w, 's> StorySchedule<'w, 's> {
+    // Empty until the events have loaded.
+    pub fn events(&self) -> &[StoryEvent] {
+        self.events.get(&self.handle.0)
+            .map(|StoryEvents(events)| events.as_slice())
+            .unwrap_or(&[])
+    }
+}
+
+// The day of the run and what it has in store: the events that already
+// happened, today's event if any, its guests still to come and the flavour
+// everyone is after today.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Calendar {
+    pub day: u32,
+    pub fired: BTreeSet<String>,
+    pub event: Option<String>,
+    pub guests: Vec<String>,
+    pub demand: Option<Taste>
+}
+
+impl Default for Calendar {
+    fn default() -> Self {
+        Calendar {
+            day: 1,
+            fired: BTreeSet::new(),
+            event: None,
+            guests: Vec::new(),
+            demand: None
+        }
+    }
+}
+
+impl Calendar {
+    // Month and day of the month, both counting from one.
+    pub fn date(&self) -> (u32, u32) {
+        let (mut month, mut day) = (START_MONTH, START_DAY + self.day - 1);
+        while day > MONTH_LENGTHS[(month - 1) as usize] {
+            day -= MONTH_LENGTHS[(month - 1) as usize];
+            month = month % 12 + 1;
+        }
+        (month, day)
+    }
+
+    pub fn weekday_key(&self) -> &'static str {
+        WEEKDAYS[((self.day - 1) % 7) as usize]
+    }
+
+    pub fn month_key(&self) -> &'static str {
+        MONTHS[(self.date().0 - 1) as usize]
+    }
+
+    pub fn season(&self) -> Season {
+        match self.date().0 {
+            12 | 1 | 2 => Season::Winter,
+            3..=5 => Season::Spring,
+            6..=8 => Season::Summer,
+            _ => Season::Autumn
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.day > DAYS
+    }
+
+    pub fn advance(&mut self) {
+        self.day += 1;
+        self.event = None;
+        self.guests.clear();
+        self.demand = None;
+    }
+
+    // Fires the first event due today that hasn't happened yet this run.
+    pub fn start_day(&mut self
+        , events: &[StoryEvent]
+        , archetypes: &[Archetype]
+        , reputation: &Reputation
+        , arcs: &Arcs)
+    {
+        let due = events.iter()
+            .filter(|event| !self.fired.contains(&event.id))
+            .find(|event| self.is_due(&event.when, archetypes, reputation, arcs));
+        if let Some(event) = due {
+            self.fired.insert(event.id.clone());
+            self.event = Some(event.name.clone());
+            // Guests are taken from the back.
+            self.guests = event.guests.iter().rev().cloned().collect();
+            self.demand = event.demand;
+        }
+    }
+
+    fn is_due(&self, trigger: &Trigger, archetypes: &[Archetype], reputation: &Reputation, arcs: &Arcs) -> bool {
+        match trigger {
+            Trigger::Day(day) => self.day == *day,
+            Trigger::Date { month, day } => self.date() == (*month, *day),
+            Trigger::Outcome { customer, outcome } => archetypes.iter()
+                .find(|archetype| &archetype.id == customer)
+                .and_then(|archetype| arcs.outcome(archetype))
+                .map(|reached| reached == outcome)
+                .unwrap_or(false),
+            Trigger::Reputation { faction, at_least } => reputation.faction(faction) >= *at_least
+        }
+    }
+}
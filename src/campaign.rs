@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+// How long a run lasts before the ending.
+pub const DAYS: u32 = 7;
+pub const CUSTOMERS_PER_DAY: u32 = 3;
+const DELIGHTED_PAY: u32 = 12;
+const DISAPPOINTED_PAY: u32 = 2;
+
+// How the current run is going: how many customers have been served today,
+// the money earned so far and how many runs came before it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Campaign {
+    pub served: u32,
+    pub money: u32,
+    #[serde(default)]
+    pub cycle: u32
+}
+
+impl Campaign {
+    // Takes payment from the customer who just left, returning whether that
+    // was the last one of the day.
+    pub fn serve(&mut self, delighted: bool) -> bool {
+        self.money += if delighted { DELIGHTED_PAY } else { DISAPPOINTED_PAY };
+        self.served += 1;
+        if self.served >= CUSTOMERS_PER_DAY {
+            self.served = 0;
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -1,7 +1,51 @@
+use bevy::prelude::*;
+use serde::Deserialize;
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+use crate::markup::Weight;
+
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
 pub enum GameState {
+    Title,
     Dialogue,
     Cooking,
-    Intermission
+    Intermission,
+    Ending,
+    Credits,
+    Paused,
+    Settings,
+    Controls,
+    Achievements,
+    Stats,
+    Mods
+}
+
+pub struct Fonts {
+    pub light: Handle<Font>,
+    pub regular: Handle<Font>,
+    pub medium: Handle<Font>,
+    pub semi_bold: Handle<Font>,
+    pub bold: Handle<Font>
+}
+
+impl Fonts {
+    pub fn load(asset_server: &AssetServer) -> Self {
+        Fonts {
+            light: asset_server.load("fonts/FiraCode-Light.ttf"),
+            regular: asset_server.load("fonts/FiraCode-Regular.ttf"),
+            medium: asset_server.load("fonts/FiraCode-Medium.ttf"),
+            semi_bold: asset_server.load("fonts/FiraCode-SemiBold.ttf"),
+            bold: asset_server.load("fonts/FiraCode-Bold.ttf")
+        }
+    }
+
+    pub fn weight(&self, weight: Weight) -> Handle<Font> {
+        match weight {
+            Weight::Light => self.light.clone(),
+            Weight::Regular => self.regular.clone(),
+            Weight::Medium => self.medium.clone(),
+            Weight::SemiBold => self.semi_bold.clone(),
+            Weight::Bold => self.bold.clone()
+        }
+    }
 }
@@ -0,0 +1,362 @@
+use bevy::prelude::*;
+
+use crate::common::*;
+use crate::cooking::CookingSelection;
+use crate::customers::{Part, Roster, Visit};
+use crate::events::IngredientSelected;
+use crate::layout::{Anchored, DESIGN_WIDTH};
+use crate::recipes::{Ingredient, RecipeList};
+use crate::save::Progress;
+
+const LINES: usize = 12;
+const LINE_HEIGHT: f32 = 18.;
+const STATES: [GameState; 12] = [GameState::Title, GameState::Dialogue, GameState::Cooking, GameState::Intermission
+    , GameState::Ending, GameState::Credits, GameState::Paused, GameState::Settings, GameState::Controls
+    , GameState::Achievements, GameState::Stats, GameState::Mods];
+const HELP: [&str; 7] = [
+    "scene <customer> [greeting|delighted|disappointed] [variant]",
+    "frame <frame>",
+    "state <state>",
+    "select <ingredient> <ingredient>",
+    "money <amount>",
+    "dump",
+    "help"
+];
+
+// What can be typed into the console. Ingredients are never used up, so
+// money is the only thing there is to grant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Scene { customer: String, part: Part, variant: usize },
+    Frame(u32),
+    State(GameState),
+    Select(u32, u32),
+    Money(u32),
+    Dump,
+    Help
+}
+
+impl Command {
+    pub fn parse(line: &str, ingredients: &[Ingredient]) -> Result<Command, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["scene", customer] => Ok(Command::Scene { customer: customer.to_string(), part: Part::Greeting, variant: 0 }),
+            ["scene", customer, part] => Ok(Command::Scene { customer: customer.to_string(), part: part_named(part)?, variant: 0 }),
+            ["scene", customer, part, variant] => Ok(Command::Scene {
+                customer: customer.to_string(),
+                part: part_named(part)?,
+                variant: number(variant)? as usize
+            }),
+            ["frame", frame] => Ok(Command::Frame(number(frame)?)),
+            ["state", state] => Ok(Command::State(state_named(state)?)),
+            ["select", first, second] => Ok(Command::Select(ingredient_named(first, ingredients)?, ingredient_named(second, ingredients)?)),
+            ["money", amount] => Ok(Command::Money(number(amount)?)),
+            ["grant", ..] => Err("Ingredients never run out, only money can be granted".to_string()),
+            ["dump"] => Ok(Command::Dump),
+            ["help"] => Ok(Command::Help),
+            [] => Err("Type help for the commands".to_string()),
+            [name, ..] if ["scene", "frame", "state", "select", "money", "dump", "help"].contains(name) =>
+                Err(format!("Wrong arguments for {}, type help for usage", name)),
+            [name, ..] => Err(format!("Unknown command {}", name))
+        }
+    }
+}
+
+fn number(word: &str) -> Result<u32, String> {
+    word.parse().map_err(|_| format!("Not a number: {}", word))
+}
+
+fn part_named(word: &str) -> Result<Part, String> {
+    match word.to_lowercase().as_str() {
+        "greeting" => Ok(Part::Greeting),
+        "delighted" => Ok(Part::Delighted),
+        "disappointed" => Ok(Part::Disappointed),
+        _ => Err(format!("No part called {}", word))
+    }
+}
+
+fn state_named(word: &str) -> Result<GameState, String> {
+    STATES.iter()
+        .find(|state| format!("{:?}", state).eq_ignore_ascii_case(word))
+        .cloned()
+        .ok_or_else(|| format!("No game state called {}", word))
+}
+
+// By number, or by name as in `ingredient.<name>`.
+fn ingredient_named(word: &str, ingredients: &[Ingredient]) -> Result<u32, String> {
+    ingredients.iter()
+        .find(|ingredient| word == ingredient.id.to_string() || ingredient.name == format!("ingredient.{}", word))
+        .map(|ingredient| ingredient.id)
+        .ok_or_else(|| format!("No ingredient called {}", word))
+}
+
+// A developer console, toggled with the grave key. Only compiled in with the
+// `console` feature.
+#[derive(Debug, Clone, Default)]
+pub struct Console {
+    pub open: bool,
+    pub input: String,
+    pub output: Vec<String>,
+    pub commands: Vec<Command>
+}
+
+impl Console {
+    pub fn print(&mut self, line: impl Into<String>) {
+        self.output.push(line.into());
+        let extra = self.output.len().saturating_sub(LINES);
+        self.output.drain(..extra);
+    }
+}
+
+// Runs between the engine reading the keyboard and the game turning it into
+// actions, so nothing typed into the console reaches the game.
+pub fn console_input(mut console: ResMut<Console>
+    , mut keys: ResMut<Input<KeyCode>>
+    , mut characters: EventReader<ReceivedCharacter>
+    , recipes: RecipeList)
+{
+    if keys.just_pressed(KeyCode::Grave) {
+        console.open = !console.open;
+        keys.clear();
+    }
+    if !console.open {
+        characters.iter().count();
+        return;
+    }
+
+    for character in characters.iter() {
+        if !character.char.is_control() && character.char != '`' {
+            console.input.push(character.char);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        console.input.pop();
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        console.open = false;
+    }
+    if keys.just_pressed(KeyCode::Return) || keys.just_pressed(KeyCode::NumpadEnter) {
+        let line = std::mem::take(&mut console.input);
+        console.print(format!("> {}", line));
+        match Command::parse(&line, recipes.ingredients()) {
+            Ok(command) => console.commands.push(command),
+            Err(error) => console.print(error)
+        }
+    }
+    keys.clear();
+}
+
+pub fn run_commands(mut console: ResMut<Console>
+    , mut progress: Progress
+    , roster: Roster
+    , mut selection: ResMut<CookingSelection>
+    , mut selected: EventWriter<IngredientSelected>
+    , mut game_state: ResMut<State<GameState>>)
+{
+    let commands = std::mem::take(&mut console.commands);
+    for command in commands {
+        let archetypes = roster.archetypes();
+        match command {
+            Command::Scene { customer, part, variant } => {
+                let archetype = match archetypes.iter().find(|archetype| archetype.id == customer) {
+                    Some(archetype) => archetype,
+                    None => {
+                        console.print(format!("No customer called {}", customer));
+                        continue;
+                    }
+                };
+                let dialogue = &mut *progress.dialogue;
+                let standing = progress.reputation.standing(archetype);
+                let variants = archetype.pool(part, standing).len();
+                if variant >= variants.max(1) {
+                    console.print(format!("{} has {} variants of that part", customer, variants));
+                    continue;
+                }
+                dialogue.customer = customer;
+                dialogue.visit = Visit::generate(archetype, progress.calendar.demand, &mut progress.rng.customers);
+                dialogue.standing = standing;
+                dialogue.chapter = None;
+                dialogue.part = part;
+                dialogue.variant = variant;
+                dialogue.frame = 0;
+                enter(&mut game_state, GameState::Dialogue);
+            }
+            Command::Frame(frame) => {
+                let maximum = progress.dialogue.maximum(archetypes);
+                if frame > maximum {
+                    console.print(format!("This scene ends at frame {}", maximum));
+                    continue;
+                }
+                progress.dialogue.frame = frame;
+                enter(&mut game_state, GameState::Dialogue);
+            }
+            Command::State(state) => enter(&mut game_state, state),
+            // The kitchen resets the selection on entering, so there's
+            // nothing to pick until it's showing.
+            Command::Select(first, second) => {
+                if *game_state.current() != GameState::Cooking {
+                    console.print("Only in the kitchen, try state cooking first");
+                    continue;
+                }
+                selection.reset();
+                selected.send(IngredientSelected(first));
+                selected.send(IngredientSelected(second));
+            }
+            Command::Money(amount) => {
+                progress.campaign.money += amount;
+                console.print(format!("Money: {}", progress.campaign.money));
+            }
+            Command::Dump => {
+                info!("{:#?}", progress.save());
+                info!("{:?} in {:?}", *selection, game_state.current());
+                console.print("Dumped the run to the log");
+            }
+            Command::Help => {
+                for usage in HELP {
+                    console.print(usage);
+                }
+            }
+        }
+    }
+}
+
+// Straight into the state, replacing any overlays, or back into it if it's
+// the one showing.
+fn enter(game_state: &mut State<GameState>, state: GameState) {
+    if *game_state.current() == state {
+        game_state.restart().ok();
+    } else {
+        game_state.replace(state).ok();
+    }
+}
+
+// Drawn over everything, only while open.
+pub fn console_display(mut commands: Commands
+    , console: Res<Console>
+    , mut texts: Query<&mut Text, With<ConsoleText>>
+    , entities: Query<Entity, With<ConsoleScene>>
+    , fonts: Res<Fonts>)
+{
+    if !console.is_changed() {
+        return;
+    }
+    if !console.open {
+        for entity in entities.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    let mut lines = console.output.clone();
+    lines.push(format!("> {}_", console.input));
+    let value = lines.join("\n");
+    if let Ok(mut text) = texts.get_single_mut() {
+        text.sections[0].value = value;
+        return;
+    }
+
+    let height = (LINES + 1) as f32*LINE_HEIGHT + 16.;
+    commands.spawn_bundle(SpriteBundle {
+        sprite: Sprite {
+            color: Color::rgba(0., 0., 0., 0.85),
+            custom_size: Some((DESIGN_WIDTH, height).into()),
+            ..default()
+        },
+        ..default()
+    }).insert(ConsoleScene)
+        .insert(Anchored::new(Vec2::new(0., 0.5), 0., -height/2., 70.));
+    commands.spawn_bundle(Text2dBundle {
+        text: Text {
+            sections: vec![TextSection {
+                value,
+                style: TextStyle {
+                    font: fonts.regular.clone(),
+                    font_size: 14.,
+                    color: Color::rgb(0.8, 1., 0.8)
+                }
+            }],
+            alignment: TextAlignment {
+                vertical: VerticalAlign::Top,
+                horizontal: HorizontalAlign::Left
+            }
+        },
+        ..default()
+    }).insert(ConsoleScene)
+        .insert(ConsoleText)
+        .insert(Anchored::new(Vec2::new(-0.5, 0.5), 12., -8., 71.));
+}
+
+#[derive(Component)]
+pub struct ConsoleScene;
+
+#[derive(Component)]
+pub struct ConsoleText;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Command, String> {
+        let ingredients: Vec<Ingredient> = ron::de::from_str(include_str!("../assets/game.ingredients.ron")).unwrap();
+        Command::parse(line, &ingredients)
+    }
+
+    #[test]
+    fn scene() {
+        assert_eq!(parse("scene lady"), Ok(Command::Scene { customer: "lady".to_string(), part: Part::Greeting, variant: 0 }));
+        assert_eq!(parse("scene lady Delighted"), Ok(Command::Scene { customer: "lady".to_string(), part: Part::Delighted, variant: 0 }));
+        assert_eq!(parse("  scene lady disappointed 2 "), Ok(Command::Scene { customer: "lady".to_string(), part: Part::Disappointed, variant: 2 }));
+        assert!(parse("scene lady farewell").is_err());
+        assert!(parse("scene lady greeting two").is_err());
+    }
+
+    #[test]
+    fn frame() {
+        assert_eq!(parse("frame 3"), Ok(Command::Frame(3)));
+        assert_eq!(parse("frame -1"), Err("Not a number: -1".to_string()));
+    }
+
+    #[test]
+    fn state() {
+        assert_eq!(parse("state cooking"), Ok(Command::State(GameState::Cooking)));
+        assert_eq!(parse("state Intermission"), Ok(Command::State(GameState::Intermission)));
+        assert_eq!(parse("state kitchen"), Err("No game state called kitchen".to_string()));
+    }
+
+    #[test]
+    fn select_by_number_or_name() {
+        assert_eq!(parse("select 1 6"), Ok(Command::Select(1, 6)));
+        assert_eq!(parse("select pumpkin coffee_beans"), Ok(Command::Select(1, 6)));
+        assert_eq!(parse("select egg 4"), Ok(Command::Select(3, 4)));
+        assert_eq!(parse("select 0 1"), Err("No ingredient called 0".to_string()));
+        assert_eq!(parse("select 1 7"), Err("No ingredient called 7".to_string()));
+        assert_eq!(parse("select 1 none"), Err("No ingredient called none".to_string()));
+    }
+
+    #[test]
+    fn money_dump_and_help() {
+        assert_eq!(parse("money 100"), Ok(Command::Money(100)));
+        assert_eq!(parse("money lots"), Err("Not a number: lots".to_string()));
+        assert_eq!(parse("grant egg 5"), Err("Ingredients never run out, only money can be granted".to_string()));
+        assert_eq!(parse("dump"), Ok(Command::Dump));
+        assert_eq!(parse("help"), Ok(Command::Help));
+    }
+
+    #[test]
+    fn wrong_argument_counts() {
+        for line in ["scene", "scene a greeting 0 1", "frame", "frame 1 2", "state", "select 1", "select 1 2 3"
+            , "money", "dump all", "help me"]
+        {
+            let name = line.split_whitespace().next().unwrap();
+            assert_eq!(parse(line), Err(format!("Wrong arguments for {}, type help for usage", name)), "{}", line);
+        }
+    }
+
+    #[test]
+    fn unknown_commands() {
+        assert_eq!(parse(""), Err("Type help for the commands".to_string()));
+        assert_eq!(parse("   "), Err("Type help for the commands".to_string()));
+        assert_eq!(parse("teleport home"), Err("Unknown command teleport".to_string()));
+        assert_eq!(parse("Help"), Err("Unknown command Help".to_string()));
+    }
+}
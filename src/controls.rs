@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+
+use crate::actions::{Action, Actions, Bindings};
+use crate::common::*;
+use crate::layout::{Anchored, DESIGN_HEIGHT, DESIGN_WIDTH};
+use crate::locale::{Locale, Localized};
+
+const DEPTH: f32 = 30.;
+const ROW_HEIGHT: f32 = 26.;
+
+pub fn controls_setup(mut commands: Commands
+    , mut cursor: ResMut<ControlsCursor>
+    , locale: Locale
+    , fonts: Res<Fonts>)
+{
+    *cursor = ControlsCursor::default();
+
+    commands.spawn_bundle(SpriteBundle {
+        sprite: Sprite {
+            color: Color::rgba(0., 0., 0., 0.9),
+            custom_size: Some((DESIGN_WIDTH, DESIGN_HEIGHT).into()),
+            ..default()
+        },
+        ..default()
+    }).insert(ControlsScene)
+        .insert(Anchored::center(0., 0., DEPTH).covering());
+
+    let title = Localized::new("controls.title", 24.);
+    let (text, effects) = title.text(&locale, &fonts);
+    commands.spawn_bundle(Text2dBundle {
+        text: text.with_alignment(TextAlignment::CENTER),
+        ..default()
+    }).insert(ControlsScene)
+        .insert(Anchored::center(0., DESIGN_HEIGHT/2. - 40., DEPTH + 1.))
+        .insert(title)
+        .insert(effects);
+
+    // One row per action and a last one to reset everything, filled in by `controls_rows`.
+    let top = (Action::ALL.len() as f32 + 1.)*ROW_HEIGHT/2.;
+    for index in 0..=Action::ALL.len() {
+        commands.spawn_bundle(Text2dBundle {
+            text: Text::default().with_alignment(TextAlignment::CENTER),
+            ..default()
+        }).insert(ControlsScene)
+            .insert(ControlsRow(index))
+            .insert(Anchored::center(0., top - index as f32*ROW_HEIGHT, DEPTH + 1.))
+            .insert(Localized::new("controls.reset", 18.));
+    }
+
+    let hint = Localized::new("controls.hint", 16.);
+    let (text, effects) = hint.text(&locale, &fonts);
+    commands.spawn_bundle(Text2dBundle {
+        text: text.with_alignment(TextAlignment::CENTER),
+        ..default()
+    }).insert(ControlsScene)
+        .insert(Anchored::center(0., -DESIGN_HEIGHT/2. + 30., DEPTH + 1.))
+        .insert(hint)
+        .insert(effects);
+}
+
+pub fn controls_rows(mut rows: Query<(&ControlsRow, &mut Localized)>
+    , cursor: Res<ControlsCursor>
+    , bindings: Res<Bindings>
+    , locale: Locale)
+{
+    if !cursor.is_changed() && !bindings.is_changed() && !locale.is_changed() {
+        return;
+    }
+    for (ControlsRow(index), mut localized) in rows.iter_mut() {
+        let mut row = match Action::ALL.get(*index) {
+            Some(action) => {
+                let number = match action {
+                    Action::SelectIngredient(n) => n.to_string(),
+                    _ => String::new()
+                };
+                let name = locale.format(action.key(), &[("number".to_string(), number)]);
+                let keys = bindings.describe(*action)
+                    .unwrap_or_else(|| locale.get("controls.unbound").to_string());
+                let capturing = cursor.capturing && cursor.selected == *index;
+                Localized::new(if capturing { "controls.capture" } else { "controls.row" }, 18.)
+                    .with_arg("action", name)
+                    .with_arg("keys", keys)
+            }
+            None => Localized::new("controls.reset", 18.)
+        };
+        if cursor.selected == *index {
+            row.color = Color::rgb(1., 0.85, 0.3);
+        }
+        if *localized != row {
+            *localized = row;
+        }
+    }
+}
+
+pub fn controls_input(mut actions: ResMut<Actions>
+    , keys: Res<Input<KeyCode>>
+    , mut bindings: ResMut<Bindings>
+    , mut cursor: ResMut<ControlsCursor>
+    , mut game_state: ResMut<State<GameState>>)
+{
+    if cursor.capturing {
+        if let Some(key) = keys.get_just_released().next() {
+            if let Some(action) = Action::ALL.get(cursor.selected) {
+                bindings.bind(*action, *key);
+            }
+            cursor.capturing = false;
+        }
+        actions.clear();
+        return;
+    }
+
+    let rows = Action::ALL.len() + 1;
+    if actions.take(Action::Up) {
+        cursor.selected = (cursor.selected + rows - 1) % rows;
+    }
+    if actions.take(Action::Down) {
+        cursor.selected = (cursor.selected + 1) % rows;
+    }
+    if actions.take(Action::Confirm) {
+        if cursor.selected < Action::ALL.len() {
+            cursor.capturing = true;
+        } else {
+            bindings.reset();
+        }
+    }
+    if actions.take(Action::Back) || actions.take(Action::Pause) {
+        game_state.pop().ok();
+    }
+    actions.clear();
+}
+
+pub fn controls_cleanup(mut commands: Commands, entities: Query<(Entity, &ControlsScene)>) {
+    for (entity, _) in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[derive(Component)]
+pub struct ControlsScene;
+
+#[derive(Component)]
+pub struct ControlsRow(pub usize);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ControlsCursor {
+    pub selected: usize,
+    pub capturing: bool
+}
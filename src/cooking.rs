@@ -1,207 +1,357 @@
 
 use std::cmp::{min, max};
+use std::fmt;
 
 use bevy::prelude::*;
 use bevy::text::Text2dBounds;
 
+use crate::actions::{Action, Actions, Bindings};
+use crate::audio::{AudioRequest, SoundEffect};
 use crate::common::*;
+use crate::customers::{Flavor, Part, Roster};
 use crate::dialogue::DialogueState;
+use crate::events::{CustomerReacted, DishCooked, DishServed, IngredientSelected};
+use crate::layout::Anchored;
+use crate::locale::{Locale, Localized};
+use crate::recipes::{Ingredient, Recipe, RecipeList, Recipes};
+use crate::reputation::Reputation;
+use crate::rng::GameRng;
+use crate::transition::Transition;
 
+// The ingredients are laid out in rows from the top, two to a row until it
+// takes a third column to fit them all.
+const ROWS: u32 = 3;
+const MIN_COLUMNS: u32 = 2;
+const MAX_COLUMNS: u32 = 3;
+pub const MAX_INGREDIENTS: usize = (ROWS*MAX_COLUMNS) as usize;
+
+fn grid_columns(count: usize) -> u32 {
+    (count as u32).div_ceil(ROWS).clamp(MIN_COLUMNS, MAX_COLUMNS)
+}
+
+// The column and row, from the bottom, of the ingredient at `index`.
+fn slot(index: usize, count: usize) -> (u32, u32) {
+    let columns = grid_columns(count);
+    (index as u32 % columns, ROWS - 1 - index as u32/columns)
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn cooking_setup(mut commands: Commands
     , mut selection: ResMut<CookingSelection>
-    , asset_server: Res<AssetServer>)
+    , mut cursor: ResMut<IngredientCursor>
+    , recipes: RecipeList
+    , bindings: Res<Bindings>
+    , asset_server: Res<AssetServer>
+    , locale: Locale
+    , fonts: Res<Fonts>)
 {
     selection.reset();
-    let description = [
-        ["Press 5", "Press 3", "Press 1"],
-        ["Press 6", "Press 4", "Press 2"]
-    ];
-
-    let font = asset_server.load("fonts/FiraCode-Regular.ttf");
-    let text_style = TextStyle {
-        font,
-        font_size: 18.,
-        color: Color::WHITE
-    };
+    let ingredients = recipes.ingredients();
+    // In case the kitchen has fewer ingredients than the last time.
+    cursor.step(0, 0, ingredients.len());
 
     // Spawn the item selections
-    for i in 0..2 {
-        for j in 0..3 {
-            let path = CookingSelection::item_path(i, j);
-            let text = description[i as usize][j as usize];
-            let (i, j) = (i as f32, j as f32);
-            commands.spawn()
-                .insert(CookingScene)
-                .insert_bundle(SpriteBundle {
-                    texture: asset_server.load(path),
-                    transform: Transform::from_xyz(i*150. + 150., j*150. - 100., 0.5)
-                        .with_scale((0.5, 0.5, 1.).into()),
-                    ..default()
-                });
+    for (index, ingredient) in ingredients.iter().enumerate() {
+        let (i, j) = slot(index, ingredients.len());
+        commands.spawn()
+            .insert(CookingScene)
+            .insert(IngredientCursor::place(i, j, 0.5)
+                .with_scale((0.5, 0.5, 1.).into()))
+            .insert_bundle(SpriteBundle {
+                texture: asset_server.load(&ingredient.icon),
+                ..default()
+            });
 
-            commands.spawn()
-                .insert(CookingScene)
-                .insert_bundle(Text2dBundle {
-                    text: Text::from_section(text, text_style.clone()),
-                    transform: Transform::from_xyz(i*150. + 122., j*150. - 165., 2.),
-                    ..default()
-                });
-
-            commands.spawn()
-                .insert(CookingScene)
-                .insert_bundle(SpriteBundle {
-                    sprite: Sprite {
-                        color: Color::rgba(0., 0., 0., 0.75),
-                        custom_size: Some((100., 25.).into()),
-                        ..default()
-                    },
-                    transform: Transform::from_xyz(i*150. + 150., j*150. - 175., 1.),
-                    ..default()
-                });
+        // Only what number keys pick get a label.
+        if bindings.keys(Action::SelectIngredient(ingredient.id)).is_empty() {
+            continue;
         }
+        let label = Localized::new("cooking.press", 18.)
+            .with_arg("number", ingredient.id);
+        let (text, effects) = label.text(&locale, &fonts);
+        let (i, j) = (i as f32, j as f32);
+        commands.spawn()
+            .insert(CookingScene)
+            .insert(label)
+            .insert(effects)
+            .insert(Anchored::right(i*150. - 418., j*150. - 165., 2.))
+            .insert_bundle(Text2dBundle {
+                text,
+                ..default()
+            });
+
+        commands.spawn()
+            .insert(CookingScene)
+            .insert(Anchored::right(i*150. - 390., j*150. - 175., 1.))
+            .insert_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(0., 0., 0., 0.75),
+                    custom_size: Some((100., 25.).into()),
+                    ..default()
+                },
+                ..default()
+            });
     }
 
     commands.spawn()
         .insert(CookingScene)
-        .insert(CombinationItem)
+        .insert(IngredientHighlight)
+        .insert(cursor.highlight_place())
         .insert_bundle(SpriteBundle {
-            transform: Transform::from_xyz(-300., 150., 0.5)
-            .with_scale((0.35, 0.35, -1.).into()),
+            sprite: Sprite {
+                color: Color::rgba(1., 0.85, 0.3, 0.5),
+                custom_size: Some((130., 130.).into()),
+                ..default()
+            },
             ..default()
         });
 
+    commands.spawn()
+        .insert(CookingScene)
+        .insert(CombinationItem)
+        .insert(Anchored::left(240., 150., 0.5)
+            .with_scale((0.35, 0.35, -1.).into()))
+        .insert_bundle(SpriteBundle::default());
+
+    let label = Localized::new("cooking.nothing_selected", 18.);
+    let (text, effects) = label.text(&locale, &fonts);
     commands.spawn()
         .insert(CookingScene)
         .insert(SelectionText)
+        .insert(label)
+        .insert(effects)
+        .insert(Anchored::right(-315., -250., 2.))
         .insert_bundle(Text2dBundle {
-            text: Text::from_section("Nothing selected.", text_style.clone())
-                .with_alignment(TextAlignment::CENTER),
-            transform: Transform::from_xyz(225., -250., 2.),
+            text: text.with_alignment(TextAlignment::CENTER),
             ..default()
         });
 
     commands.spawn()
         .insert(CookingScene)
+        .insert(Anchored::right(-315., -250., 1.))
         .insert_bundle(SpriteBundle {
             sprite: Sprite {
                 color: Color::rgba(0., 0., 0., 0.75),
                 custom_size: Some((250., 25.).into()),
                 ..default()
             },
-            transform: Transform::from_xyz(225., -250., 1.),
             ..default()
         });
 
+    let label = Localized::new("cooking.confirm", 18.);
+    let (text, effects) = label.text(&locale, &fonts);
     commands.spawn()
         .insert(CookingScene)
+        .insert(label)
+        .insert(effects)
+        .insert(Anchored::right(-315., -300., 2.))
         .insert_bundle(Text2dBundle {
-            text: Text::from_section("Return to confirm selection.", text_style.clone())
-                .with_alignment(TextAlignment::CENTER),
-            transform: Transform::from_xyz(225., -300., 2.),
+            text: text.with_alignment(TextAlignment::CENTER),
             ..default()
         });
 
     commands.spawn()
         .insert(CookingScene)
+        .insert(Anchored::right(-315., -300., 1.))
         .insert_bundle(SpriteBundle {
             sprite: Sprite {
                 color: Color::rgba(0., 0., 0., 0.75),
                 custom_size: Some((300., 25.).into()),
                 ..default()
             },
-            transform: Transform::from_xyz(225., -300., 1.),
             ..default()
         });
 
+    let label = Localized::new("recipe.none", 18.);
+    let (text, effects) = label.text(&locale, &fonts);
     commands.spawn()
         .insert(CookingScene)
         .insert(CombinationDescription)
+        .insert(label)
+        .insert(effects)
+        .insert(Anchored::left(240., -200., 3.))
         .insert_bundle(Text2dBundle {
-            text: Text::from_section("Combination description.", text_style)
-                .with_alignment(TextAlignment::CENTER),
+            text: text.with_alignment(TextAlignment::CENTER),
             text_2d_bounds: Text2dBounds {
                 size: (380., 300.).into()
             },
-            transform: Transform::from_xyz(-300., -200., 3.),
             ..default()
         });
 
     commands.spawn()
         .insert(CookingScene)
+        .insert(Anchored::left(240., -200., 1.))
         .insert_bundle(SpriteBundle {
             sprite: Sprite {
                 color: Color::rgba(0., 0., 0., 0.75),
                 custom_size: Some((400., 300.).into()),
                 ..default()
             },
-            transform: Transform::from_xyz(-300., -200., 1.),
             ..default()
         });
 
     commands.spawn_bundle(SpriteBundle {
             texture: asset_server.load("backgrounds/cooking.png"),
-            transform: Transform::from_scale((0.71, 0.71, 0.).into()),
             ..default()
-        }).insert(CookingScene);
+        }).insert(CookingScene)
+        .insert(Anchored::center(0., 0., 0.).with_scale((0.71, 0.71, 0.).into()).covering());
+}
+
+// Moves the highlight over the ingredient grid and turns `Select` into picking
+// whatever is under it, for `cook` to handle like a number key.
+pub fn cooking_cursor(mut actions: ResMut<Actions>
+    , mut cursor: ResMut<IngredientCursor>
+    , recipes: RecipeList
+    , mut highlight: Query<&mut Anchored, With<IngredientHighlight>>)
+{
+    let ingredients = recipes.ingredients();
+    if actions.take(Action::Left) {
+        cursor.step(-1, 0, ingredients.len());
+    }
+    if actions.take(Action::Right) {
+        cursor.step(1, 0, ingredients.len());
+    }
+    if actions.take(Action::Up) {
+        cursor.step(0, 1, ingredients.len());
+    }
+    if actions.take(Action::Down) {
+        cursor.step(0, -1, ingredients.len());
+    }
+    if actions.take(Action::Select) {
+        if let Some(ingredient) = cursor.ingredient(ingredients) {
+            actions.press(Action::SelectIngredient(ingredient));
+        }
+    }
+
+    if cursor.is_changed() {
+        for mut place in highlight.iter_mut() {
+            *place = cursor.highlight_place();
+        }
+    }
+}
+
+pub fn cook(mut actions: ResMut<Actions>
+    , selection: Res<CookingSelection>
+    , recipes: RecipeList
+    , mut selected: EventWriter<IngredientSelected>
+    , mut cooked: EventWriter<DishCooked>)
+{
+    while let Some(ingredient) = actions.take_selection() {
+        selected.send(IngredientSelected(ingredient));
+    }
+    let confirm_selection = actions.take(Action::Confirm);
+    actions.clear();
+
+    if confirm_selection {
+        let (_, _, sweet, savory, spooky) = selection.combination_data(recipes.recipes());
+        cooked.send(DishCooked {
+            selection: selection.clone(),
+            flavor: Flavor::new(sweet, savory, spooky)
+        });
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
-pub fn cook(mut combination: Query<&mut Handle<Image>, With<CombinationItem>>
-    , mut combination_text: Query<(&mut Text, &CombinationDescription), Without<SelectionText>>
-    , mut selection_text: Query<(&mut Text, &SelectionText), Without<CombinationDescription>>
+pub fn select_ingredient(mut combination: Query<&mut Handle<Image>, With<CombinationItem>>
+    , mut combination_text: Query<&mut Localized, (With<CombinationDescription>, Without<SelectionText>)>
+    , mut selection_text: Query<&mut Localized, (With<SelectionText>, Without<CombinationDescription>)>
     , asset_server: Res<AssetServer>
-    , mut input: ResMut<Input<KeyCode>>
+    , mut selected: EventReader<IngredientSelected>
     , mut selection: ResMut<CookingSelection>
-    , mut dialogue_state: ResMut<DialogueState>
-    , mut game_state: ResMut<State<GameState>>
-    , font: Res<Handle<Font>>)
+    , recipes: RecipeList
+    , mut sounds: EventWriter<AudioRequest>)
 {
-    let mut confirm_selection = false;
     let mut selection_updated = false;
-    for code in input.get_just_released() {
-        let new_selected = match code {
-            KeyCode::Key1 | KeyCode::Numpad1 => 1,
-            KeyCode::Key2 | KeyCode::Numpad2 => 2,
-            KeyCode::Key3 | KeyCode::Numpad3 => 3,
-            KeyCode::Key4 | KeyCode::Numpad4 => 4,
-            KeyCode::Key5 | KeyCode::Numpad5 => 5,
-            KeyCode::Key6 | KeyCode::Numpad6 => 6,
-            KeyCode::Return => { 
-                confirm_selection = true;
-                break
-            }
-            _ => 0
-        };
-        if new_selected != 0 {
-            selection.update(new_selected);
-            selection_updated = true;
+    for IngredientSelected(ingredient) in selected.iter() {
+        match selection.update(*ingredient, recipes.ingredients()) {
+            Ok(()) => selection_updated = true,
+            Err(error) => warn!("Ignoring a selection: {}", error)
         }
     }
-    input.clear();
+    if !selection_updated {
+        return;
+    }
 
-    let (combo_path, combo_description, sweet, savory, spooky) = selection.combination_data();
-    if selection_updated {
-        let mut combo_image = combination.single_mut();
-        *combo_image = asset_server.load(combo_path);
+    let recipes = recipes.recipes();
+    let (combo_path, combo_description, ..) = selection.combination_data(recipes);
+    let mut combo_image = combination.single_mut();
+    *combo_image = asset_server.load(combo_path);
+
+    let mut combo_text = combination_text.single_mut();
+    combo_text.key = combo_description.to_string();
 
-        let (mut combo_text, _) = combination_text.single_mut();
-        let text_style = TextStyle {
-            font: font.clone(),
-            font_size: 18.,
-            color: Color::WHITE
-        };
-        *combo_text = Text::from_section(combo_description, text_style)
-            .with_alignment(TextAlignment::CENTER);
+    let mut selected_text = selection_text.single_mut();
+    *selected_text = selection.localized();
 
-        let (mut selected_text, _) = selection_text.single_mut();
-        let new_selected_text = selection.text(&font);
-        *selected_text = new_selected_text;
+    let sound = match combo_path {
+        "" => SoundEffect::Select,
+        _ if selection.is_dish(recipes) => SoundEffect::Success,
+        _ => SoundEffect::Failure
+    };
+    sounds.send(AudioRequest::Effect(sound));
+}
+
+// Shows the edited recipe for the pair on the counter when the recipes
+// file changes.
+pub fn reload_recipes(mut combination: Query<&mut Handle<Image>, With<CombinationItem>>
+    , mut combination_text: Query<&mut Localized, With<CombinationDescription>>
+    , mut loaded: EventReader<AssetEvent<Recipes>>
+    , asset_server: Res<AssetServer>
+    , selection: Res<CookingSelection>
+    , recipes: RecipeList)
+{
+    if loaded.iter().count() == 0 {
+        return;
     }
-    
-    if confirm_selection {
-        dialogue_state.next_scene(sweet, savory, spooky);
-        game_state.set(GameState::Dialogue).ok();
+    let (combo_path, combo_description, ..) = selection.combination_data(recipes.recipes());
+    for mut combo_image in combination.iter_mut() {
+        *combo_image = asset_server.load(combo_path);
     }
+    for mut combo_text in combination_text.iter_mut() {
+        if combo_text.key != combo_description {
+            combo_text.key = combo_description.to_string();
+        }
+    }
+}
+
+// Hands the dish to the customer, who decides what they make of it.
+#[allow(clippy::too_many_arguments)]
+pub fn serve_dish(mut cooked: EventReader<DishCooked>
+    , mut served: EventWriter<DishServed>
+    , mut reacted: EventWriter<CustomerReacted>
+    , mut sounds: EventWriter<AudioRequest>
+    , mut dialogue_state: ResMut<DialogueState>
+    , mut reputation: ResMut<Reputation>
+    , mut rng: ResMut<GameRng>
+    , roster: Roster
+    , game_state: Res<State<GameState>>
+    , mut transition: ResMut<Transition>)
+{
+    let archetypes = roster.archetypes();
+    // Only the last dish counts if more than one was cooked this frame.
+    let dish = match cooked.iter().last() {
+        Some(dish) => dish,
+        None => return
+    };
+    sounds.send(AudioRequest::Effect(SoundEffect::Confirm));
+
+    let outcome = |state: &DialogueState| state.archetype(archetypes)
+        .and_then(|archetype| state.arcs.outcome(archetype))
+        .map(|outcome| outcome.to_string());
+    let before = outcome(&dialogue_state);
+    if let Err(error) = dialogue_state.next_scene(archetypes, &mut reputation, dish.flavor, &mut rng.cooking) {
+        error!("Serving a dish: {}", error);
+    }
+    served.send(DishServed {
+        customer: dialogue_state.customer.clone(),
+        selection: dish.selection.clone()
+    });
+    reacted.send(CustomerReacted {
+        customer: dialogue_state.customer.clone(),
+        delighted: dialogue_state.part == Part::Delighted,
+        outcome: outcome(&dialogue_state).filter(|_| before.is_none())
+    });
+    transition.start(game_state.current(), GameState::Dialogue);
 }
 
 pub fn cooking_cleanup(mut commands: Commands, entities: Query<(Entity, &CookingScene)>) {
@@ -222,6 +372,55 @@ pub struct CombinationDescription;
 #[derive(Component)]
 pub struct SelectionText;
 
+#[derive(Component)]
+pub struct IngredientHighlight;
+
+// Where the highlight sits on the ingredient grid. Kept between visits to the
+// kitchen so it is where the player left it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IngredientCursor {
+    pub column: u32,
+    pub row: u32
+}
+
+impl IngredientCursor {
+    pub fn place(column: u32, row: u32, z: f32) -> Anchored {
+        Anchored::right(column as f32*150. - 390., row as f32*150. - 100., z)
+    }
+
+    pub fn highlight_place(&self) -> Anchored {
+        IngredientCursor::place(self.column, self.row, 0.25)
+    }
+
+    // Stops at the edges of a grid of `count` ingredients.
+    pub fn step(&mut self, columns: i32, rows: i32, count: usize) {
+        self.column = (self.column as i32 + columns).clamp(0, grid_columns(count) as i32 - 1) as u32;
+        self.row = (self.row as i32 + rows).clamp(0, ROWS as i32 - 1) as u32;
+    }
+
+    // Nothing, over the gaps at the end of a last row that isn't full.
+    pub fn ingredient(&self, ingredients: &[Ingredient]) -> Option<u32> {
+        let columns = grid_columns(ingredients.len());
+        let index = (ROWS - 1 - self.row)*columns + self.column;
+        ingredients.get(index as usize).map(|ingredient| ingredient.id)
+    }
+}
+
+// Ingredient numbers come from key bindings and the console too, so they
+// can be out of range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KitchenError {
+    UnknownIngredient(u32)
+}
+
+impl fmt::Display for KitchenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KitchenError::UnknownIngredient(ingredient) => write!(f, "there is no ingredient {}", ingredient)
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct CookingSelection(pub u32, pub u32);
 
@@ -232,134 +431,116 @@ impl CookingSelection {
         *second = 0;
     }
 
-    pub fn update(&mut self, next: u32) {
+    // Leaves the selection as it was if there's no such ingredient.
+    pub fn update(&mut self, next: u32, ingredients: &[Ingredient]) -> Result<(), KitchenError> {
+        CookingSelection::ingredient(next, ingredients)?;
         let CookingSelection(first, second) = self;
         *first = *second;
         *second = next;
+        Ok(())
     }
 
-    pub fn item_path(i: u32, j: u32) -> &'static str {
-        match (i, j) {
-            (0, 0) => "items/chocolate.png",
-            (1, 0) => "items/coffee_beans.png",
-            (0, 1) => "items/egg.png",
-            (1, 1) => "items/grapes.png",
-            (0, 2) => "items/pumpkin.png",
-            (1, 2) => "items/skull.png",
-            _ => panic!("Impossible")
-        }
+    pub fn ingredient(id: u32, ingredients: &[Ingredient]) -> Result<&Ingredient, KitchenError> {
+        ingredients.iter()
+            .find(|ingredient| ingredient.id == id)
+            .ok_or(KitchenError::UnknownIngredient(id))
+    }
+
+    // Whether the current combination makes an actual dish rather than a failure.
+    pub fn is_dish(&self, recipes: &[Recipe]) -> bool {
+        let (path, ..) = self.combination_data(recipes);
+        !path.is_empty() && path != "combinations/failure.png"
     }
 
-    pub fn text(&self, font: &Handle<Font>) -> Text {
+    pub fn localized(&self) -> Localized {
         let CookingSelection(first, second) = self;
-        let text = format!("Select ingredients {} and {}", first, second);
-        let text_style = TextStyle {
-            font: font.clone(),
-            font_size: 18.,
-            color: Color::WHITE
-        };
-        Text::from_section(text, text_style)
-            .with_alignment(TextAlignment::CENTER)
-    }
-
-    // 1: Pumpkin
-    // 2: Skull
-    // 3: Egg
-    // 4: Grapes
-    // 5: Chocolate
-    // 6: Coffee Beans
-    pub fn combination_data(&self) -> (&'static str, &'static str, u32, u32, u32) {
+        Localized::new("cooking.selected", 18.)
+            .with_arg("first", first)
+            .with_arg("second", second)
+    }
+
+    // The recipe the pair makes, or an empty pot if there's none.
+    pub fn combination_data<'a>(&self, recipes: &'a [Recipe]) -> (&'a str, &'a str, u32, u32, u32) {
         let CookingSelection(first, second) = self;
-        let (i, j) = (min(first, second), max(first, second));
-        match (i, j) {
-            (1, 1) => ("combinations/pumpkin_mash.png",
-                r#"Like mom's mash potatoes but with the superior vegetable. Pumpkin mash is the perfect fall side dish to round out any meaty plate."#
-            , 35, 60, 5
-            ),
-            (1, 2) => ("combinations/failure.png",
-                r#"In a puff of smoke, pumpkins and skulls just don't seem to mix!"#
-                , 0, 0, 0
-            ),
-            (1, 3) => ("combinations/pumpkin_pie.png",
-                r#"A classic favorite. Pumpkin pie is delicious but not too sweet. The kids will love it and their teeth will too."#
-                , 70, 30, 0
-            ),
-            (1, 4) => ("combinations/failure.png",
-                r#"In a puff of smoke, pumpkins and grapes just don't seem to mix!"#
-                , 0, 0, 0
-            ),
-            (1, 5) => ("combinations/pumpkin_spice_chocolate_bar.png",
-                r#"Chocolate is always better cut with an earthy flavor, but make no mistake these candy bars are packed with sugar! Enjoy your lovable pumpkin aroma in a new chocolatey way."#
-                , 85, 15, 0
-            ),
-            (1, 6) => ("combinations/pumpkin_spice_latte.png",
-                r#"Not just for your coffee enthusiast! Pumpkin spice is as Halloween as it gets. Don't sleep on a tasty spicy treat that's quite sweet."#
-                , 50, 50, 0
-            ),
-            (2, 2) => ("combinations/failure.png",
-                r#"In a puff of smoke, skulls just don't seem to mix!"#
-                , 0, 0, 0
-            ),
-            (2, 3) => ("combinations/failure.png",
-                r#"In a puff of smoke, skull and egg just doesn't seem to mix!"#
-                , 0, 0, 0
-            ),
-            (2, 4) => ("combinations/bone_marrow_wine.png",
-                r#"What's better than an afternoon in front of the fire place with a cool glass of Bone Marrow Wine? Unlike other wine's you might try this one is infused with the life force of a once living being. Perfect for your fledgling vampire friends."#
-                , 0, 60, 40
-            ),
-            (2, 5) => ("combinations/chocolate_skull_fondue.png",
-                r#"Chocolate fondue is great on it's own, so why not pair it with the dread of death? Skull fondue is like ecstasy with a dash of existential crisis. Zombies seem to love it the most!"#
-                , 60, 0, 40
-            ),
-            (2, 6) => ("combinations/failure.png",
-                r#"In a puff of smoke, skull and coffee beans just doesn't seem to mix!"#
-                , 0, 0, 0
-            ),
-            (3, 3) => ("combinations/egg_over_easy.png",
-                r#"Eggs over easy is as old as bread. Can you really go wrong with this one? Just enjoy yourself a nice lightly seasoned egg already!"#
-                , 5, 90, 5
-            ),
-            (3, 4) => ("combinations/egg_salad.png",
-                r#"Grapes in an egg salad are the perfect sweet kick to an otherwise savory meal. Just imagine they're eye balls if it's not gruesome enough for you!"#
-                , 5, 80, 15
-            ),
-            (3, 5) => ("combinations/chocolate_chip_cookie.png",
-                r#"Chocolate chip cookies are the perfect treat for a young boy and girl. Especially if you have to fatten them up first!"#
-                , 100, 0, 0
-            ),
-            (3, 6) => ("combinations/egg_coffee.png",
-                r#"Egg coffee is known for it's double layers, a bottom layer of coffee and a top layer of tasty egg cream. Check your moustaches after this one fellas."#
-                , 90, 10, 0
-            ),
-            (4, 4) => ("combinations/grape_jam.png",
-                r#"Grape jam is what brings everybody together! Is that a pigeon I see sitting on the lid of the jar? Hey, hey, what are you doing! Stop stealing my grape jam! I spent valuable hours on that! Hey! Stop it!"#
-                , 60, 20, 20
-            ),
-            (4, 5) => ("combinations/chocolate_covered_grapes.png",
-                r#"The only thing better than chocolate covered strawberries are chocolate covered grapes! Did I remind you to just imagine they're eyeballs yet?"#
-                , 95, 5, 0
-            ),
-            (4, 6) => ("combinations/failure.png",
-                r#"In a puff of smoke, grapes and coffee beans just doesn't seem to mix!"#
-                , 0, 0, 0
-            ),
-            (5, 5) => ("combinations/molten_dark_chocolate_candy.png",
-                r#"Nothing is as delicious as a chocolate ball filled with molten chocolate. This lava is so hot it will sear right through your mandible. You'll scream in agony as you go in for another bite. The horror of it all is irresistible!"#
-                , 80, 0, 20
-            ),
-            (5, 6) => ("combinations/chocolate_espresso.png",
-                r#"On the move with a sweet tooth? Chocolate espresso is lot a shot of pure adrenaline!"#
-                , 50, 50, 0
-            ),
-            (6, 6) => ("combinations/black_coffee.png",
-                r#"One time I a werewolf told me that black coffee was the only solution to a transformation hangover. If it's just alcohol or something worse, give a straight black coffee a try to restart your senses."#
-                , 0, 100, 0
-            ),
-            _ => ("",
-                r#"Combination description."#
-                , 0, 0, 0
-            ),
+        let ingredients = (*min(first, second), *max(first, second));
+        match recipes.iter().find(|recipe| recipe.ingredients == ingredients) {
+            Some(recipe) => (&recipe.image, &recipe.description, recipe.sweet, recipe.savory, recipe.spooky),
+            None => ("", "recipe.none", 0, 0, 0)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ingredients() -> Vec<Ingredient> {
+        ron::de::from_str(include_str!("../assets/game.ingredients.ron")).unwrap()
+    }
+
+    fn ingredient(id: u32) -> Ingredient {
+        Ingredient { id, icon: String::new(), name: String::new() }
+    }
+
+    #[test]
+    fn ingredients_in_range() {
+        let ingredients = ingredients();
+        assert_eq!(CookingSelection::ingredient(1, &ingredients).map(|ingredient| ingredient.icon.as_str()), Ok("items/pumpkin.png"));
+        assert_eq!(CookingSelection::ingredient(6, &ingredients).map(|ingredient| ingredient.name.as_str()), Ok("ingredient.coffee_beans"));
+    }
+
+    #[test]
+    fn ingredients_out_of_range() {
+        let ingredients = ingredients();
+        for id in [0, 7] {
+            assert_eq!(CookingSelection::ingredient(id, &ingredients), Err(KitchenError::UnknownIngredient(id)));
+        }
+    }
+
+    #[test]
+    fn update_shifts_the_selection() {
+        let ingredients = ingredients();
+        let mut selection = CookingSelection(0, 0);
+        assert_eq!(selection.update(3, &ingredients), Ok(()));
+        assert_eq!(selection.update(5, &ingredients), Ok(()));
+        assert_eq!(selection, CookingSelection(3, 5));
+    }
+
+    #[test]
+    fn failed_update_keeps_the_selection() {
+        let ingredients = ingredients();
+        let mut selection = CookingSelection(2, 4);
+        for id in [0, 7] {
+            assert_eq!(selection.update(id, &ingredients), Err(KitchenError::UnknownIngredient(id)));
+            assert_eq!(selection, CookingSelection(2, 4));
+        }
+    }
+
+    #[test]
+    fn six_ingredients_in_two_columns() {
+        let ingredients = ingredients();
+        // As the kitchen was first drawn: 1 and 2 on top, 5 and 6 at the bottom.
+        let grid: Vec<Vec<Option<u32>>> = (0..2)
+            .map(|column| (0..3).map(|row| IngredientCursor { column, row }.ingredient(&ingredients)).collect())
+            .collect();
+        assert_eq!(grid, vec![
+            vec![Some(5), Some(3), Some(1)],
+            vec![Some(6), Some(4), Some(2)]
+        ]);
+    }
+
+    #[test]
+    fn more_ingredients_take_a_third_column() {
+        let ingredients: Vec<Ingredient> = (1..=7).map(ingredient).collect();
+        assert_eq!(slot(0, 7), (0, 2));
+        assert_eq!(slot(2, 7), (2, 2));
+        assert_eq!(slot(6, 7), (0, 0));
+        assert_eq!(IngredientCursor { column: 1, row: 0 }.ingredient(&ingredients), None);
+
+        let mut cursor = IngredientCursor::default();
+        cursor.step(5, 5, 7);
+        assert_eq!(cursor, IngredientCursor { column: 2, row: 2 });
+        cursor.step(0, 0, 6);
+        assert_eq!(cursor, IngredientCursor { column: 1, row: 2 });
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::arcs::Chapter;
+use crate::mods::Content;
+use crate::reputation::{Reputation, Standing};
+use crate::rng::Rng;
+
+// The most of a flavour the customer asked to avoid that they'll put up with.
+const AVOID_LIMIT: f32 = 30.;
+const ORDER_TEMPLATES: usize = 3;
+
+// Either how a dish tastes, in percent, or how much a customer cares about each flavour.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Flavor {
+    pub sweet: f32,
+    pub savory: f32,
+    pub spooky: f32
+}
+
+impl Flavor {
+    pub fn new(sweet: u32, savory: u32, spooky: u32) -> Self {
+        Flavor {
+            sweet: sweet as f32,
+            savory: savory as f32,
+            spooky: spooky as f32
+        }
+    }
+
+    // How well `dish` matches this preference, out of 100 for weights summing to one.
+    pub fn score(&self, dish: Flavor) -> f32 {
+        self.sweet*dish.sweet + self.savory*dish.savory + self.spooky*dish.spooky
+    }
+
+    pub fn of(&self, taste: Taste) -> f32 {
+        match taste {
+            Taste::Sweet => self.sweet,
+            Taste::Savory => self.savory,
+            Taste::Spooky => self.spooky
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Taste {
+    #[default]
+    Sweet,
+    Savory,
+    Spooky
+}
+
+impl Taste {
+    pub const ALL: [Taste; 3] = [Taste::Sweet, Taste::Savory, Taste::Spooky];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Taste::Sweet => "flavor.sweet",
+            Taste::Savory => "flavor.savory",
+            Taste::Spooky => "flavor.spooky"
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Mood {
+    Cheerful,
+    #[default]
+    Neutral,
+    Grumpy
+}
+
+impl Mood {
+    pub fn key(&self) -> &'static str {
+        match self {
+            Mood::Cheerful => "mood.cheerful",
+            Mood::Neutral => "mood.neutral",
+            Mood::Grumpy => "mood.grumpy"
+        }
+    }
+
+    // Scales how forgiving the customer is.
+    pub fn leeway(&self) -> f32 {
+        match self {
+            Mood::Cheerful => 1.25,
+            Mood::Neutral => 1.,
+            Mood::Grumpy => 0.75
+        }
+    }
+}
+
+// What the customer asks for this time, and the string id of how they word it.
+// On a day when everyone is after one flavour they are only judged on that.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Order {
+    pub line: String,
+    pub flavor: Taste,
+    pub avoid: Option<Taste>,
+    #[serde(default)]
+    pub demand: Option<Taste>
+}
+
+// The parts of a visit that are rolled when the customer walks in.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Visit {
+    pub name: String,
+    pub mood: Mood,
+    pub order: Order
+}
+
+impl Visit {
+    pub fn generate(archetype: &Archetype, demand: Option<Taste>, rng: &mut Rng) -> Self {
+        let name = rng.pick(&archetype.names).unwrap_or(&archetype.name).clone();
+        let mood = match rng.weighted(&[1., 2., 1.]) {
+            Some(0) => Mood::Cheerful,
+            Some(2) => Mood::Grumpy,
+            _ => Mood::Neutral
+        };
+
+        // They ask for what they like most and sometimes warn off something they don't care for.
+        let preference = archetype.preference;
+        let flavor = Taste::ALL.into_iter()
+            .max_by(|a, b| preference.of(*a).partial_cmp(&preference.of(*b)).unwrap_or(std::cmp::Ordering::Equal))
+            .unwrap_or_default();
+        let disliked: Vec<Taste> = Taste::ALL.into_iter()
+            .filter(|taste| preference.of(*taste) <= 0.)
+            .collect();
+        let avoid = if rng.chance(0.6) { rng.pick(&disliked).copied() } else { None };
+        let kind = if avoid.is_some() { "avoid" } else { "plain" };
+        let line = format!("order.{}.{}", kind, rng.below(ORDER_TEMPLATES));
+
+        let order = match demand {
+            Some(taste) => Order {
+                line: format!("order.demand.{}", rng.below(ORDER_TEMPLATES)),
+                flavor: taste,
+                avoid: None,
+                demand
+            },
+            None => Order { line, flavor, avoid, demand }
+        };
+        Visit { name, mood, order }
+    }
+
+    pub fn accepts(&self, archetype: &Archetype, dish: Flavor) -> bool {
+        let tolerance = archetype.tolerance*self.mood.leeway();
+        let avoided = self.order.avoid
+            .map(|taste| dish.of(taste) <= AVOID_LIMIT)
+            .unwrap_or(true);
+        let score = match self.order.demand {
+            Some(taste) => dish.of(taste),
+            None => archetype.preference.score(dish)
+        };
+        score > 100. - tolerance && avoided
+    }
+}
+
+// Which part of a visit is being played.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Part {
+    #[default]
+    Greeting,
+    Delighted,
+    Disappointed
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Portraits {
+    pub neutral: String,
+    pub delighted: String,
+    pub disappointed: String,
+    // Multiplied into the portraits, so one drawing can serve several customers.
+    #[serde(default = "untinted")]
+    pub tint: (f32, f32, f32)
+}
+
+fn untinted() -> (f32, f32, f32) {
+    (1., 1., 1.)
+}
+
+fn always() -> f32 {
+    1.
+}
+
+// One kind of customer. The dialogue pools hold alternative conversations,
+// each a list of line string ids.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Archetype {
+    pub id: String,
+    pub name: String,
+    // Given names for their visits; `name` is used when there are none.
+    #[serde(default)]
+    pub names: Vec<String>,
+    // Who they gossip with about the shop.
+    #[serde(default)]
+    pub faction: Option<String>,
+    pub portraits: Portraits,
+    pub preference: Flavor,
+    // How far below a perfect 100 a dish may score and still delight them.
+    pub tolerance: f32,
+    pub greetings: Vec<Vec<String>>,
+    // Greetings for when they think well or badly of the shop, if they have any.
+    #[serde(default)]
+    pub fond: Vec<Vec<String>>,
+    #[serde(default)]
+    pub wary: Vec<Vec<String>>,
+    pub delighted: Vec<Vec<String>>,
+    pub disappointed: Vec<Vec<String>>,
+    // Their story, if they have one, starting from the first chapter.
+    #[serde(default)]
+    pub arc: Vec<Chapter>,
+    // How often they come by compared to the others.
+    #[serde(default = "always")]
+    pub frequency: f32
+}
+
+impl Archetype {
+    pub fn pool(&self, part: Part, standing: Standing) -> &[Vec<String>] {
+        match (part, standing) {
+            (Part::Greeting, Standing::Fond) if !self.fond.is_empty() => &self.fond,
+            (Part::Greeting, Standing::Wary) if !self.wary.is_empty() => &self.wary,
+            (Part::Greeting, _) => &self.greetings,
+            (Part::Delighted, _) => &self.delighted,
+            (Part::Disappointed, _) => &self.disappointed
+        }
+    }
+
+    pub fn chapter(&self, id: &str) -> Option<&Chapter> {
+        self.arc.iter().find(|chapter| chapter.id == id)
+    }
+
+    pub fn portrait(&self, part: Part) -> &str {
+        match part {
+            Part::Greeting => &self.portraits.neutral,
+            Part::Delighted => &self.portraits.delighted,
+            Part::Disappointed => &self.portraits.disappointed
+        }
+    }
+
+    pub fn tint(&self) -> Color {
+        let (r, g, b) = self.portraits.tint;
+        Color::rgb(r, g, b)
+    }
+}
+
+// Every customer, loaded from `shop.customers.ron`.
+#[derive(Debug, Clone, Default, TypeUuid)]
+#[uuid = "8e3c1a52-7f0d-4b9e-a6c4-2d5e8f1b3a70"]
+pub struct Customers(pub Vec<Archetype>);
+
+#[derive(Default)]
+pub struct CustomersLoader;
+
+impl AssetLoader for CustomersLoader {
+    fn load<'a>(&'a self
+        , bytes: &'a [u8]
+        , load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), anyhow::Error>>
+    {
+        Box::pin(async move {
+            let archetypes: Vec<Archetype> = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(Customers(archetypes)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["customers.ron"]
+    }
+}
+
+pub struct CustomersHandle(pub Handle<Customers>);
+
+impl CustomersHandle {
+    pub fn load(asset_server: &AssetServer) -> Self {
+        CustomersHandle(asset_server.load("shop.customers.ron"))
+    }
+}
+
+#[derive(SystemParam)]
+pub struct Roster<'w, 's> {
+    content: Res<'w, Content>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>
+}
+
+impl<'w, 's> Roster<'w, 's> {
+    // Empty until the customers, and those of any mods, have loaded.
+    pub fn archetypes(&self) -> &[Archetype] {
+        &self.content.archetypes
+    }
+}
+
+// How many times each customer has come by this run, by archetype id.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Visits(pub HashMap<String, u32>);
+
+impl Visits {
+    pub fn count(&self, id: &str) -> u32 {
+        let Visits(visits) = self;
+        visits.get(id).copied().unwrap_or(0)
+    }
+
+    pub fn record(&mut self, id: &str) {
+        *self.0.entry(id.to_string()).or_insert(0) += 1;
+    }
+}
+
+// Picks the next customer by how often they come and what they think of the
+// shop, favouring those who have been by less and skipping whoever was just
+// here unless they are the only one.
+pub fn next_customer<'a>(archetypes: &'a [Archetype]
+    , visits: &Visits
+    , reputation: &Reputation
+    , current: &str
+    , rng: &mut Rng) -> Option<&'a Archetype>
+{
+    let weights: Vec<f32> = archetypes.iter()
+        .map(|archetype| {
+            if archetypes.len() > 1 && archetype.id == current {
+                0.
+            } else {
+                archetype.frequency*reputation.appeal(archetype)/(visits.count(&archetype.id) + 1) as f32
+            }
+        })
+        .collect();
+    rng.weighted(&weights).map(|index| &archetypes[index])
+}
@@ -1,51 +1,91 @@
 
+use std::collections::HashSet;
+use std::fmt;
+
 use bevy::prelude::*;
 use bevy::text::Text2dBounds;
+use serde::{Deserialize, Serialize};
 
+use crate::actions::{Action, Actions, Bindings};
+use crate::arcs::Arcs;
+use crate::calendar::Calendar;
+use crate::campaign::DAYS;
 use crate::common::*;
+use crate::customers::{next_customer, Archetype, Customers, Flavor, Part, Roster, Visit, Visits};
+use crate::events::LineShown;
+use crate::layout::{Anchored, DESIGN_HEIGHT, DESIGN_WIDTH};
+use crate::locale::{Locale, Localized};
+use crate::markup;
+use crate::reputation::{Reputation, Standing};
+use crate::rng::Rng;
+use crate::save::Progress;
+use crate::storage;
+use crate::transition::Transition;
+
+const READ_DELAY: f32 = 1.5;
+const READ_DELAY_PER_CHARACTER: f32 = 0.04;
+const SKIP_DELAY: f32 = 0.1;
+// Stands in for a line that can't be found.
+pub const MISSING_LINE: &str = "dialogue.missing";
 
+#[allow(clippy::too_many_arguments)]
 pub fn dialogue_setup(mut commands: Commands
-    , windows: Res<Windows>
     , asset_server: Res<AssetServer>
     , state: Res<DialogueState>
-    , font: Res<Handle<Font>>)
+    , autoplay: Res<DialogueAutoplay>
+    , bindings: Res<Bindings>
+    , mut shown: EventWriter<LineShown>
+    , calendar: Res<Calendar>
+    , roster: Roster
+    , locale: Locale
+    , fonts: Res<Fonts>)
 {
-    let window = windows.primary();
-    let (width, height) = (window.width(), window.height());
+    let archetypes = roster.archetypes();
+    shown.send(LineShown {
+        customer: state.customer.clone(),
+        line: state.text(archetypes).to_string()
+    });
+    let (width, height) = (DESIGN_WIDTH, DESIGN_HEIGHT);
     let scale = height/2160.;
-    let left_character_place = Transform::from_xyz(-width/2. + width/4., 0., 0.5)
+    let left_character_place = Anchored::new(Vec2::new(-0.25, 0.), 0., 0., 0.5)
         .with_scale((scale, scale, 1.).into());
-    let right_character_place = Transform::from_xyz(width/2. - width/4., 0., 0.5)
+    let right_character_place = Anchored::new(Vec2::new(0.25, 0.), 0., 0., 0.5)
         .with_scale((scale, scale, 1.).into());
     
     commands.spawn()
         .insert(DialogueScene)
+        .insert(left_character_place)
         .insert_bundle(SpriteBundle {
             texture: asset_server.load(state.left_character_path()),
-            transform: left_character_place,
             ..default()
         });
     
+    let customer = state.archetype(archetypes);
     commands.spawn()
         .insert(DialogueScene)
+        .insert(DialoguePortrait)
+        .insert(right_character_place)
         .insert_bundle(SpriteBundle {
-            texture: asset_server.load(state.right_character_path()),
-            transform: right_character_place,
+            texture: state.right_character_path(archetypes)
+                .map(|path| asset_server.load(path))
+                .unwrap_or_default(),
+            sprite: Sprite {
+                color: customer.map(|customer| customer.tint()).unwrap_or(Color::WHITE),
+                ..default()
+            },
             ..default()
         });
 
-    let text_style = TextStyle {
-        font: font.clone(),
-        font_size: height/40.,
-        color: Color::WHITE
-    };
+    let font_size = height/40.;
     let text_alignment = TextAlignment::CENTER;
-    let text_place = |z| Transform::from_xyz(0., -height/4. - height/16., z);
+    let text_place = |z| Anchored::bottom(0., height/4. - height/16., z);
     let outer_box_size = (width - width/4., height/4.);
     let text_size = (width - width/3., height/4. - height/12.);
     let text_bounds = Text2dBounds { 
         size: text_size.into()
     };
+    let mode_place = Anchored::bottom(width/2. - width/8. - width/12., height/4. + height/12., 2.);
+    let name_place = Anchored::bottom(-(width/2. - width/8. - width/12.), height/4. + height/12., 2.);
 
     commands.spawn_bundle(SpriteBundle {
         sprite: Sprite {
@@ -53,51 +93,243 @@ pub fn dialogue_setup(mut commands: Commands
             custom_size: Some(outer_box_size.into()),
             ..default()
         },
-        transform: Transform::from_xyz(0., -height/4. - height/16., 1.),
         ..default()
-    }).insert(DialogueScene);
+    }).insert(DialogueScene)
+        .insert(text_place(1.));
     
+    let mut line = Localized::new(state.text(archetypes), font_size);
+    line.args = visit_args(&state.visit, &locale);
+    let (text, effects) = line.text(&locale, &fonts);
     commands.spawn_bundle(Text2dBundle {
-        text: Text::from_section(state.text(), text_style).with_alignment(text_alignment),
+        text: text.with_alignment(text_alignment),
         text_2d_bounds: text_bounds,
-        transform: text_place(2.),
         ..default()
     }).insert(DialogueScene)
-        .insert(DialogueText);
+        .insert(text_place(2.))
+        .insert(DialogueText)
+        .insert(line)
+        .insert(effects);
+
+    let mut mode = Localized::new(autoplay.mode.key(), font_size);
+    mode.args = mode_args(&bindings, &locale);
+    let (text, effects) = mode.text(&locale, &fonts);
+    commands.spawn_bundle(Text2dBundle {
+        text: text.with_alignment(text_alignment),
+        ..default()
+    }).insert(DialogueScene)
+        .insert(mode_place)
+        .insert(DialogueModeText)
+        .insert(mode)
+        .insert(effects);
+
+    if customer.is_some() {
+        let mut name = Localized::new("dialogue.plate", font_size);
+        name.args = visit_args(&state.visit, &locale);
+        let (text, effects) = name.text(&locale, &fonts);
+        commands.spawn_bundle(Text2dBundle {
+            text: text.with_alignment(text_alignment),
+            ..default()
+        }).insert(DialogueScene)
+            .insert(name_place)
+            .insert(name)
+            .insert(effects);
+    }
+
+    let (_, date) = calendar.date();
+    let mut headings = vec![Localized::new("calendar.heading", font_size)
+        .with_arg("weekday", locale.get(calendar.weekday_key()))
+        .with_arg("month", locale.get(calendar.month_key()))
+        .with_arg("date", date)
+        .with_arg("season", locale.get(calendar.season().key()))
+        .with_arg("day", calendar.day)
+        .with_arg("days", DAYS)];
+    if let Some(event) = &calendar.event {
+        headings.push(Localized::new(event, font_size*1.25));
+    }
+    for (index, heading) in headings.into_iter().enumerate() {
+        let (text, effects) = heading.text(&locale, &fonts);
+        commands.spawn_bundle(Text2dBundle {
+            text: text.with_alignment(text_alignment),
+            ..default()
+        }).insert(DialogueScene)
+            .insert(Anchored::new(Vec2::new(0., 0.5), 0., -height/24. - index as f32*font_size*1.75, 2.))
+            .insert(heading)
+            .insert(effects);
+    }
 
     commands.spawn_bundle(SpriteBundle {
         texture: asset_server.load("backgrounds/dialogue.png"),
-        transform: Transform::from_scale((0.71, 0.71, 0.).into()),
         ..default()
-    }).insert(DialogueScene);
+    }).insert(DialogueScene)
+        .insert(Anchored::center(0., 0., 0.).with_scale((0.71, 0.71, 0.).into()).covering());
 }
 
-pub fn dialogue_text(mut text: Query<&mut Text, With<DialogueText>>
+pub fn dialogue_text(mut text: Query<&mut Localized, (With<DialogueText>, Without<DialogueModeText>)>
+    , mut mode_text: Query<&mut Localized, (With<DialogueModeText>, Without<DialogueText>)>
     , state: Res<DialogueState>
-    , font: Res<Handle<Font>>)
+    , autoplay: Res<DialogueAutoplay>
+    , bindings: Res<Bindings>
+    , roster: Roster
+    , locale: Locale)
 {
-    let text_style = TextStyle {
-        font: font.clone(),
-        font_size: 18.,
-        color: Color::WHITE
-    };
-    let text_alignment = TextAlignment::CENTER;
+    let line = state.text(roster.archetypes());
+    let args = visit_args(&state.visit, &locale);
     let mut text = text.single_mut();
-    *text.as_mut() = Text::from_section(state.text(), text_style).with_alignment(text_alignment);
+    if text.key != line || text.args != args {
+        text.key = line.to_string();
+        text.args = args;
+    }
+    let mode_args = mode_args(&bindings, &locale);
+    let mut mode_text = mode_text.single_mut();
+    if mode_text.key != autoplay.mode.key() || mode_text.args != mode_args {
+        mode_text.key = autoplay.mode.key().to_string();
+        mode_text.args = mode_args;
+    }
 }
 
-pub fn dialogue_next(mut state: ResMut<DialogueState>
-    , mut keys: ResMut<Input<KeyCode>>
+// The keys that toggle each mode, as they're bound right now.
+fn mode_args(bindings: &Bindings, locale: &Locale) -> Vec<(String, String)> {
+    [("auto", Action::ToggleAuto), ("skip", Action::ToggleSkip)].into_iter()
+        .map(|(name, action)| {
+            let keys = bindings.describe(action)
+                .unwrap_or_else(|| locale.get("controls.unbound").to_string());
+            (name.to_string(), keys)
+        })
+        .collect()
+}
+
+// Keeps the scene going when the customers file changes: `dialogue_text`
+// picks up the edited line, `recover_scene` steps back if the scene got
+// shorter and this redraws the customer.
+pub fn reload_dialogue(mut loaded: EventReader<AssetEvent<Customers>>
+    , state: Res<DialogueState>
+    , mut portraits: Query<(&mut Handle<Image>, &mut Sprite), With<DialoguePortrait>>
+    , asset_server: Res<AssetServer>
+    , roster: Roster)
+{
+    if loaded.iter().count() == 0 {
+        return;
+    }
+    let archetypes = roster.archetypes();
+    for (mut texture, mut sprite) in portraits.iter_mut() {
+        *texture = state.right_character_path(archetypes)
+            .map(|path| asset_server.load(path))
+            .unwrap_or_default();
+        sprite.color = state.archetype(archetypes)
+            .map(|customer| customer.tint())
+            .unwrap_or(Color::WHITE);
+    }
+}
+
+// A save or a mod can name a customer or a line that isn't there (any more).
+// The placeholder shows for a frame at most: an unknown customer makes way
+// for the next one and a scene that got shorter steps back to its last line.
+pub fn recover_scene(mut progress: Progress
+    , mut logged: Local<Option<SceneError>>
+    , roster: Roster
     , mut game_state: ResMut<State<GameState>>)
 {
-    if keys.get_just_released().count() > 0 {
-        match state.next_frame() {
-            TransitionTo::Cooking => game_state.set(GameState::Cooking).ok(),
-            TransitionTo::Intermission => game_state.set(GameState::Intermission).ok(),
-            TransitionTo::Dialogue => None
-        };
+    let archetypes = roster.archetypes();
+    let error = match progress.dialogue.line(archetypes) {
+        Ok(_) => {
+            *logged = None;
+            return;
+        }
+        Err(error) => error
+    };
+    if logged.as_ref() != Some(&error) {
+        error!("Dialogue: {}", error);
+        *logged = Some(error.clone());
+    }
+    match error {
+        SceneError::UnknownCustomer(_) if !archetypes.is_empty() => {
+            progress.next_customer(archetypes);
+            game_state.restart().ok();
+        }
+        SceneError::MissingLine { frame, .. } => {
+            let maximum = progress.dialogue.maximum(archetypes);
+            if frame > maximum {
+                progress.dialogue.frame = maximum;
+            }
+        }
+        _ => ()
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn dialogue_next(mut state: ResMut<DialogueState>
+    , mut actions: ResMut<Actions>
+    , game_state: Res<State<GameState>>
+    , mut transition: ResMut<Transition>
+    , mut autoplay: ResMut<DialogueAutoplay>
+    , mut read_lines: ResMut<ReadLines>
+    , mut shown: EventWriter<LineShown>
+    , roster: Roster
+    , locale: Locale
+    , time: Res<Time>)
+{
+    let archetypes = roster.archetypes();
+    if actions.take(Action::ToggleAuto) {
+        autoplay.toggle(DialogueMode::Auto, locale.get(state.text(archetypes)));
+    }
+    if actions.take(Action::ToggleSkip) {
+        autoplay.toggle(DialogueMode::Skip, locale.get(state.text(archetypes)));
+    }
+    let pressed = actions.take(Action::Advance);
+    actions.clear();
+    if transition.is_running() {
+        return;
+    }
+
+    let elapsed = autoplay.timer.tick(time.delta()).finished();
+    let mode = autoplay.mode;
+    let advance = match mode {
+        DialogueMode::Manual => pressed,
+        DialogueMode::Auto => pressed || elapsed,
+        DialogueMode::Skip => {
+            // Skipping only fast-forwards through lines that were already read
+            // and hands control back before a request or anything new.
+            let skippable = read_lines.contains(state.text(archetypes)) && !state.is_choice(archetypes);
+            if pressed || !skippable {
+                autoplay.mode = DialogueMode::Manual;
+            }
+            skippable && elapsed && !pressed
+        }
+    };
+
+    if advance {
+        read_lines.mark(state.text(archetypes));
+        match state.next_frame(archetypes) {
+            TransitionTo::Cooking => transition.start(game_state.current(), GameState::Cooking),
+            TransitionTo::Intermission => transition.start(game_state.current(), GameState::Intermission),
+            TransitionTo::Dialogue => shown.send(LineShown {
+                customer: state.customer.clone(),
+                line: state.text(archetypes).to_string()
+            })
+        }
     }
-    keys.clear();
+}
+
+// Gives the reader time for each new line before autoplay moves on.
+pub fn restart_autoplay(mut shown: EventReader<LineShown>
+    , mut autoplay: ResMut<DialogueAutoplay>
+    , locale: Locale)
+{
+    if let Some(LineShown { line, .. }) = shown.iter().last() {
+        autoplay.restart(locale.get(line));
+    }
+}
+
+// What the order templates and name plate fill in, already in the current
+// language. Given names aren't string ids and come through unchanged.
+fn visit_args(visit: &Visit, locale: &Locale) -> Vec<(String, String)> {
+    let order = &visit.order;
+    vec![
+        ("name".to_string(), locale.get(&visit.name).to_string()),
+        ("mood".to_string(), locale.get(visit.mood.key()).to_string()),
+        ("flavor".to_string(), locale.get(order.flavor.key()).to_string()),
+        ("avoid".to_string(), order.avoid.map(|taste| locale.get(taste.key())).unwrap_or_default().to_string())
+    ]
 }
 
 pub fn dialogue_cleanup(mut commands: Commands, entities: Query<(Entity, &DialogueScene)>) {
@@ -109,11 +341,123 @@ pub fn dialogue_cleanup(mut commands: Commands, entities: Query<(Entity, &Dialog
 #[derive(Component)]
 pub struct DialogueText;
 
+#[derive(Component)]
+pub struct DialogueModeText;
+
+// The customer's side of the scene.
+#[derive(Component)]
+pub struct DialoguePortrait;
+
 #[derive(Component)]
 pub struct DialogueScene;
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct DialogueState(pub u32, pub u32);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DialogueMode {
+    Manual,
+    Auto,
+    Skip
+}
+
+impl DialogueMode {
+    pub fn key(&self) -> &'static str {
+        match self {
+            DialogueMode::Manual => "dialogue.mode.manual",
+            DialogueMode::Auto => "dialogue.mode.auto",
+            DialogueMode::Skip => "dialogue.mode.skip"
+        }
+    }
+}
+
+pub struct DialogueAutoplay {
+    pub mode: DialogueMode,
+    pub timer: Timer
+}
+
+impl Default for DialogueAutoplay {
+    fn default() -> Self {
+        DialogueAutoplay {
+            mode: DialogueMode::Manual,
+            timer: Timer::from_seconds(READ_DELAY, false)
+        }
+    }
+}
+
+impl DialogueAutoplay {
+    pub fn toggle(&mut self, mode: DialogueMode, text: &str) {
+        self.mode = if self.mode == mode { DialogueMode::Manual } else { mode };
+        self.restart(text);
+    }
+
+    pub fn restart(&mut self, text: &str) {
+        let seconds = match self.mode {
+            DialogueMode::Skip => SKIP_DELAY,
+            _ => READ_DELAY + markup::length(text) as f32 * READ_DELAY_PER_CHARACTER
+        };
+        self.timer = Timer::from_seconds(seconds, false);
+    }
+}
+
+// The string id of every line the player has advanced past, kept across sessions.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadLines(pub HashSet<String>);
+
+impl ReadLines {
+    pub fn load() -> Self {
+        storage::load("read_lines")
+    }
+
+    pub fn contains(&self, line: &str) -> bool {
+        let ReadLines(lines) = self;
+        lines.contains(line)
+    }
+
+    pub fn mark(&mut self, line: &str) {
+        if self.0.insert(line.to_string()) {
+            storage::store("read_lines", self);
+        }
+    }
+}
+
+// Why a scene can't be played as the state describes it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SceneError {
+    UnknownCustomer(String),
+    MissingLine { customer: String, part: Part, variant: usize, frame: u32 },
+    // A dish was served outside of a greeting.
+    NoOrder(Part)
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::UnknownCustomer(customer) => write!(f, "there is no customer called {:?}", customer),
+            SceneError::MissingLine { customer, part, variant, frame } =>
+                write!(f, "{} has no line {} in variant {} of {:?}", customer, frame, variant, part),
+            SceneError::NoOrder(part) => write!(f, "a dish was served during {:?}", part)
+        }
+    }
+}
+
+// The customer being served, who they are and what they want today, how
+// they feel about the shop, the chapter of their story this visit plays if
+// they have one, which part of their visit is playing, which conversation
+// from that part's pool and the line within it. Alongside is how far every
+// customer's story has come this run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DialogueState {
+    pub customer: String,
+    #[serde(default)]
+    pub visit: Visit,
+    #[serde(default)]
+    pub standing: Standing,
+    #[serde(default)]
+    pub chapter: Option<String>,
+    pub part: Part,
+    pub variant: usize,
+    pub frame: u32,
+    #[serde(default)]
+    pub arcs: Arcs
+}
 
 pub enum TransitionTo {
     Cooking,
@@ -122,113 +466,242 @@ pub enum TransitionTo {
 }
 
 impl DialogueState {
+    pub fn archetype<'a>(&self, archetypes: &'a [Archetype]) -> Option<&'a Archetype> {
+        archetypes.iter().find(|archetype| archetype.id == self.customer)
+    }
+
+    fn lines<'a>(&self, archetypes: &'a [Archetype]) -> &'a [String] {
+        let archetype = match self.archetype(archetypes) {
+            Some(archetype) => archetype,
+            None => return &[]
+        };
+        let chapter = self.chapter.as_deref()
+            .and_then(|id| archetype.chapter(id))
+            .map(|chapter| chapter.lines(self.part))
+            .filter(|lines| !lines.is_empty());
+        chapter.or_else(|| {
+            archetype.pool(self.part, self.standing).get(self.variant).map(|lines| lines.as_slice())
+        }).unwrap_or(&[])
+    }
+
+    // Where the customer places their order in a greeting: just before the
+    // witch's last line, so she still gets to answer it.
+    fn order_frame(&self, archetypes: &[Archetype]) -> Option<usize> {
+        let lines = self.lines(archetypes);
+        if self.is_cooking_scene() && !lines.is_empty() && !self.visit.order.line.is_empty() {
+            Some(lines.len() - 1)
+        } else {
+            None
+        }
+    }
+
     pub fn left_character_path(&self) -> &'static str {
         "characters/witch.png"
     }
 
-    pub fn right_character_path(&self) -> &'static str {
-        let DialogueState(id, _) = self;
-        match id {
-            0 | 1 | 2 => "characters/lady.png",
-            3 | 4 | 5 => "characters/guard.png",
-            6 | 7 | 8 => "characters/ghost.png",
-            _ => panic!("Impossible")
-        }
-    }
-
-    pub fn maximum(&self) -> u32 {
-        let DialogueState(id, _) = self;
-        match id {
-            0 => 1,
-            1 => 1,
-            2 => 3,
-            3 => 3,
-            4 => 1,
-            5 => 3,
-            6 => 3,
-            7 => 2,
-            8 => 3,
-            _ => 0,
-        }
-    }
-
-    pub fn text(&self) -> &'static str {
-        let DialogueState(id, frame) = self;
-        match (id, frame) {
-            (0, 0) => "Witch's Brew! Welcome to the village, I'm Matilda. I've always wanted to try the sweets made by witches back home and never hade the chance. Please would you mind brewing me something sweet?",
-            (0, 1) => "Of course! Coming right up.",
-            
-            (1, 0) => "Oh my, this is positively delightful, I love it! Thank you so much, my sweet tooth is very satisfied",
-            (1, 1) => "You're welcome! Come back again soon!",
-            
-            (2, 0) => "Ah, you really haven't perfected your craft yet have you? This isn't that sweet at all...",
-            (2, 1) => "...",
-            (2, 2) => "Oh don't worry dear, I'll come by tomorrow to give you more practice.",
-            (2, 3) => "...",
-
-            (3, 0) => "Lady witch, I'm part of Manor's guard for Duke Trichondri.",
-            (3, 1) => "Oh, uh, welcome sir, what can I do for you?",
-            (3, 2) => "I need something savory this evening Lady witch, please indulge me.",
-            (3, 3) => "Okay! Coming right up!",
-
-            (4, 0) => "Splendid! I've never had such a savory meal since my mother's home cooking! You have done a splendid job Lady witch, I'll be sure to tell my fellow guardsmen to visit your Witch's Brew.",
-            (4, 1) => "You're too kind sir! You'll make me blush.",
-
-            (5, 0) => "Disaster! You call this savory? What are you thinking!? You shouldn't be let near an ingredient or a stew for the rest of your life!",
-            (5, 1) => "Dear sir! That is quite disrespectful!",
-            (5, 2) => "Harumph! Maybe next time learn your way around salt shaker and seasoning palette!",
-            (5, 3) => "...",
-
-            (6, 0) => "BOO! Give me something spooky or I'll haunt you for the rest of your life!",
-            (6, 1) => "Aww, you're so cute, I don't think I would mind!",
-            (6, 2) => "Wait, hold on, I really want a spooky meal though! Listen, I'll haunt all your customers so they never return! Haha!",
-            (6, 3) => "Alright! Alright! Calm down, I'll whip up something spooky just for you",
-
-            (7, 0) => "Spooooky! I love it! I'll scare so many new people with this, Mwahahaha!",
-            (7, 1) => "I'm glad you like it! Don't scare my customers okay?",
-            (7, 2) => "No promises!",
-
-            (8, 0) => "You call THIS spooky!? You call THIS scary!? I've never been so insulted in my afterlife!",
-            (8, 1) => "Ah, wait! Give me a second chance cute ghost!",
-            (8, 2) => "There are no second changes in the afterlife!",
-            (8, 3) => "Isn't the afterlife a second chance?...",
-
-            _ => "You're not suppose to see this."
-        }
-    }
-
-    pub fn next_scene(&mut self, sweet: u32, savory: u32, spooky: u32) {
-        let DialogueState(scene, frame) = self;
-        *frame = 0;
-        match (*scene, sweet, savory, spooky) {
-            (0, x, _, _) if x > 75 => { *scene = 1; }
-            (0, _, _, _) => { *scene = 2; }
-            (1, _, _, _) | (2, _, _, _) => { *scene = 3; }
-            (3, _, x, _) if x > 55 => { *scene = 4; }
-            (3, _, _, _) => { *scene = 5; }
-            (4, _, _, _) | (5, _, _, _) => { *scene = 6; }
-            (6, _, _, x) if x > 20 => { *scene = 7; }
-            (6, _, _, _) => { *scene = 8; }
-            (7, _, _, _) | (8, _, _, _) => { *scene = 0; }
-            _ => panic!("Impossible!")
-        }
-    }
-
-    pub fn next_frame(&mut self) -> TransitionTo {
-        let maximum = self.maximum();
-        let mut last_frame = true;
-        let DialogueState(scene, frame) = self;
-        if *frame < maximum {
-            *frame += 1;
-            last_frame = false;
-        }
-        let cooking_scene = *scene == 0 || *scene == 3 || *scene == 6;
-        if last_frame && cooking_scene {
+    pub fn right_character_path<'a>(&self, archetypes: &'a [Archetype]) -> Result<&'a str, SceneError> {
+        self.archetype(archetypes)
+            .map(|archetype| archetype.portrait(self.part))
+            .ok_or_else(|| SceneError::UnknownCustomer(self.customer.clone()))
+    }
+
+    pub fn maximum(&self, archetypes: &[Archetype]) -> u32 {
+        let order = self.order_frame(archetypes).is_some() as usize;
+        (self.lines(archetypes).len() + order).saturating_sub(1) as u32
+    }
+
+    // The string id of the current line, or the placeholder if it can't be
+    // found. `recover_scene` logs why.
+    pub fn text<'a>(&'a self, archetypes: &'a [Archetype]) -> &'a str {
+        self.line(archetypes).unwrap_or(MISSING_LINE)
+    }
+
+    pub fn line<'a>(&'a self, archetypes: &'a [Archetype]) -> Result<&'a str, SceneError> {
+        if self.archetype(archetypes).is_none() {
+            return Err(SceneError::UnknownCustomer(self.customer.clone()));
+        }
+        let lines = self.lines(archetypes);
+        let frame = self.frame as usize;
+        let line = match self.order_frame(archetypes) {
+            Some(order) if frame == order => Some(&self.visit.order.line),
+            Some(order) if frame > order => lines.get(frame - 1),
+            _ => lines.get(frame)
+        };
+        line.map(|line| line.as_str()).ok_or_else(|| SceneError::MissingLine {
+            customer: self.customer.clone(),
+            part: self.part,
+            variant: self.variant,
+            frame: self.frame
+        })
+    }
+
+    // After a greeting the customer reacts to the dish. Once they have, the
+    // next customer comes in through `next_customer`.
+    pub fn next_scene(&mut self
+        , archetypes: &[Archetype]
+        , reputation: &mut Reputation
+        , dish: Flavor
+        , rng: &mut Rng) -> Result<(), SceneError>
+    {
+        if !self.is_cooking_scene() {
+            return Err(SceneError::NoOrder(self.part));
+        }
+        let archetype = self.archetype(archetypes);
+        let delighted = archetype
+            .map(|archetype| self.visit.accepts(archetype, dish))
+            .unwrap_or(false);
+        if let Some(archetype) = archetype {
+            reputation.serve(archetype, delighted);
+            self.arcs.advance(archetype, delighted);
+        }
+        self.part = if delighted { Part::Delighted } else { Part::Disappointed };
+        self.variant = self.pick_variant(archetypes, rng);
+        self.frame = 0;
+        // Still disappointed, so the scene plays out and moves on.
+        match archetype {
+            Some(_) => Ok(()),
+            None => Err(SceneError::UnknownCustomer(self.customer.clone()))
+        }
+    }
+
+    // Today's guests come first, then whoever the regulars' habits bring in.
+    pub fn next_customer(&mut self
+        , archetypes: &[Archetype]
+        , visits: &mut Visits
+        , reputation: &Reputation
+        , calendar: &mut Calendar
+        , rng: &mut Rng)
+    {
+        let guest = calendar.guests.pop()
+            .and_then(|guest| archetypes.iter().find(|archetype| archetype.id == guest));
+        let next = match guest.or_else(|| next_customer(archetypes, visits, reputation, &self.customer, rng)) {
+            Some(archetype) => archetype,
+            None => return
+        };
+        self.customer = next.id.clone();
+        self.visit = Visit::generate(next, calendar.demand, rng);
+        self.standing = reputation.standing(next);
+        self.chapter = self.arcs.chapter(next).map(|chapter| chapter.id.clone());
+        self.part = Part::Greeting;
+        self.variant = self.pick_variant(archetypes, rng);
+        self.frame = 0;
+        visits.record(&self.customer);
+    }
+
+    fn pick_variant(&self, archetypes: &[Archetype], rng: &mut Rng) -> usize {
+        let variants = self.archetype(archetypes)
+            .map(|archetype| archetype.pool(self.part, self.standing).len())
+            .unwrap_or(0);
+        rng.below(variants)
+    }
+
+    pub fn is_cooking_scene(&self) -> bool {
+        self.part == Part::Greeting
+    }
+
+    // The last line of a customer's request, after which the player has to cook.
+    pub fn is_choice(&self, archetypes: &[Archetype]) -> bool {
+        self.frame >= self.maximum(archetypes) && self.is_cooking_scene()
+    }
+
+    pub fn next_frame(&mut self, archetypes: &[Archetype]) -> TransitionTo {
+        let maximum = self.maximum(archetypes);
+        if self.frame < maximum {
+            self.frame += 1;
+            TransitionTo::Dialogue
+        } else if self.is_cooking_scene() {
             TransitionTo::Cooking
-        } else if last_frame && !cooking_scene {
-            TransitionTo::Intermission
         } else {
-            TransitionTo::Dialogue
+            TransitionTo::Intermission
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archetypes() -> Vec<Archetype> {
+        ron::de::from_str(include_str!("../assets/shop.customers.ron")).unwrap()
+    }
+
+    fn lady(part: Part) -> DialogueState {
+        DialogueState {
+            customer: "lady".to_string(),
+            part,
+            ..default()
         }
     }
+
+    #[test]
+    fn plays_the_lines_in_order() {
+        let archetypes = archetypes();
+        let mut state = lady(Part::Greeting);
+        assert_eq!(state.line(&archetypes), Ok("dialogue.lady.request.0"));
+        state.frame = 1;
+        assert_eq!(state.line(&archetypes), Ok("dialogue.lady.request.1"));
+        assert_eq!(state.right_character_path(&archetypes), Ok("characters/lady.png"));
+    }
+
+    #[test]
+    fn unknown_customer() {
+        let archetypes = archetypes();
+        let state = DialogueState {
+            customer: "nobody".to_string(),
+            ..default()
+        };
+        let error = SceneError::UnknownCustomer("nobody".to_string());
+        assert_eq!(state.line(&archetypes), Err(error.clone()));
+        assert_eq!(state.right_character_path(&archetypes), Err(error));
+        assert_eq!(state.text(&archetypes), MISSING_LINE);
+    }
+
+    #[test]
+    fn frame_past_the_last_line() {
+        let archetypes = archetypes();
+        let mut state = lady(Part::Greeting);
+        state.frame = state.maximum(&archetypes) + 1;
+        assert_eq!(state.line(&archetypes), Err(SceneError::MissingLine {
+            customer: "lady".to_string(),
+            part: Part::Greeting,
+            variant: 0,
+            frame: state.frame
+        }));
+        assert_eq!(state.text(&archetypes), MISSING_LINE);
+    }
+
+    #[test]
+    fn variant_past_the_last_conversation() {
+        let archetypes = archetypes();
+        let mut state = lady(Part::Delighted);
+        state.variant = 5;
+        assert!(matches!(state.line(&archetypes), Err(SceneError::MissingLine { variant: 5, .. })));
+    }
+
+    #[test]
+    fn serving_outside_a_greeting() {
+        let archetypes = archetypes();
+        let mut reputation = Reputation::default();
+        let mut rng = Rng::new(0);
+        for part in [Part::Delighted, Part::Disappointed] {
+            let mut state = lady(part);
+            let before = state.clone();
+            assert_eq!(state.next_scene(&archetypes, &mut reputation, Flavor::default(), &mut rng), Err(SceneError::NoOrder(part)));
+            assert_eq!(state, before);
+        }
+    }
+
+    #[test]
+    fn serving_an_unknown_customer() {
+        let archetypes = archetypes();
+        let mut state = DialogueState {
+            customer: "nobody".to_string(),
+            frame: 1,
+            ..default()
+        };
+        let result = state.next_scene(&archetypes, &mut Reputation::default(), Flavor::default(), &mut Rng::new(0));
+        assert_eq!(result, Err(SceneError::UnknownCustomer("nobody".to_string())));
+        assert_eq!(state.part, Part::Disappointed);
+        assert_eq!(state.frame, 0);
+    }
 }
@@ -0,0 +1,220 @@
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+use bevy::text::Text2dBounds;
+use serde::{Deserialize, Serialize};
+
+use crate::actions::{Action, Actions};
+use crate::campaign::{Campaign, DAYS};
+use crate::common::*;
+use crate::customers::{Archetype, Roster};
+use crate::dialogue::DialogueState;
+use crate::layout::{Anchored, DESIGN_HEIGHT, DESIGN_WIDTH};
+use crate::locale::{Locale, Localized};
+use crate::reputation::Reputation;
+use crate::save::SaveGame;
+use crate::storage;
+use crate::transition::Transition;
+
+const CREDITS: [&str; 5] = ["credits.title", "credits.jam", "credits.engine", "credits.fonts", "credits.thanks"];
+const CREDITS_ROW_HEIGHT: f32 = 48.;
+const CREDITS_SPEED: f32 = 40.;
+// How well the town has to think of the witch, on average, to remember her fondly.
+const BELOVED: i32 = 20;
+// Less money than this at the end of the run and the shop can't stay open.
+const CLOSING_MONEY: u32 = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Ending {
+    Famous,
+    Beloved,
+    Haunted,
+    Humble,
+    Closed
+}
+
+impl Ending {
+    pub fn key(&self) -> &'static str {
+        match self {
+            Ending::Famous => "ending.famous",
+            Ending::Beloved => "ending.beloved",
+            Ending::Haunted => "ending.haunted",
+            Ending::Humble => "ending.humble",
+            Ending::Closed => "ending.closed"
+        }
+    }
+
+    // Picks the ending a finished run has earned from the money made, what the
+    // customers think of the shop and how their stories turned out.
+    pub fn choose(campaign: &Campaign, reputation: &Reputation, dialogue_state: &DialogueState, archetypes: &[Archetype]) -> Ending {
+        let outcomes: Vec<&str> = archetypes.iter()
+            .filter_map(|archetype| dialogue_state.arcs.outcome(archetype))
+            .collect();
+        let told = archetypes.iter().filter(|archetype| !archetype.arc.is_empty()).count();
+        let standing = archetypes.iter().map(|archetype| reputation.of(archetype)).sum::<i32>()
+            / archetypes.len().max(1) as i32;
+
+        if outcomes.contains(&"haunting") && standing < 0 {
+            Ending::Haunted
+        } else if campaign.money < CLOSING_MONEY {
+            Ending::Closed
+        } else if outcomes.contains(&"patron") && outcomes.len() == told && standing >= BELOVED {
+            Ending::Famous
+        } else if standing >= BELOVED {
+            Ending::Beloved
+        } else {
+            Ending::Humble
+        }
+    }
+}
+
+// What finishing runs has unlocked, kept apart from the save so starting over
+// doesn't lose it: every ending seen, how many runs were finished and what
+// the town thought of the witch at the end of the last one, which New Game+
+// starts from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Unlocks {
+    pub endings: BTreeSet<Ending>,
+    pub cycles: u32,
+    pub reputation: Reputation
+}
+
+impl Unlocks {
+    pub fn load() -> Self {
+        storage::load("unlocks")
+    }
+
+    pub fn new_game_plus(&self) -> bool {
+        self.cycles > 0
+    }
+
+    pub fn finish(&mut self, ending: Ending, reputation: &Reputation) {
+        self.endings.insert(ending);
+        self.cycles += 1;
+        self.reputation = reputation.clone();
+        storage::store("unlocks", self);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn ending_setup(mut commands: Commands
+    , asset_server: Res<AssetServer>
+    , campaign: Res<Campaign>
+    , reputation: Res<Reputation>
+    , dialogue_state: Res<DialogueState>
+    , roster: Roster
+    , mut unlocks: ResMut<Unlocks>
+    , locale: Locale
+    , fonts: Res<Fonts>)
+{
+    let ending = Ending::choose(&campaign, &reputation, &dialogue_state, roster.archetypes());
+    unlocks.finish(ending, &reputation);
+    // The run is over, so there's nothing left to continue.
+    SaveGame::clear();
+
+    let completed = roster.archetypes().iter()
+        .filter(|archetype| dialogue_state.arcs.outcome(archetype).is_some())
+        .count();
+    let lines = [
+        (Localized::new(format!("{}.title", ending.key()), 32.), 180.),
+        (Localized::new(format!("{}.text", ending.key()), 20.), 40.),
+        (Localized::new("ending.summary", 18.)
+            .with_arg("days", DAYS)
+            .with_arg("money", campaign.money)
+            .with_arg("stories", completed), -120.),
+        (Localized::new("ending.continue", 16.), -200.)
+    ];
+    for (line, y) in lines {
+        let (text, effects) = line.text(&locale, &fonts);
+        commands.spawn_bundle(Text2dBundle {
+            text: text.with_alignment(TextAlignment::CENTER),
+            text_2d_bounds: Text2dBounds {
+                size: (DESIGN_WIDTH*2./3., DESIGN_HEIGHT/3.).into()
+            },
+            ..default()
+        }).insert(EndingScene)
+            .insert(Anchored::center(0., y, 1.))
+            .insert(line)
+            .insert(effects);
+    }
+
+    commands.spawn_bundle(SpriteBundle {
+        texture: asset_server.load("backgrounds/dialogue.png"),
+        sprite: Sprite {
+            color: Color::rgb(0.35, 0.35, 0.35),
+            ..default()
+        },
+        ..default()
+    }).insert(EndingScene)
+        .insert(Anchored::center(0., 0., 0.).with_scale((0.71, 0.71, 0.).into()).covering());
+}
+
+pub fn ending_input(mut actions: ResMut<Actions>
+    , game_state: Res<State<GameState>>
+    , mut transition: ResMut<Transition>)
+{
+    if actions.take(Action::Advance) || actions.take(Action::Confirm) {
+        transition.start(game_state.current(), GameState::Credits);
+    }
+    actions.clear();
+}
+
+pub fn ending_cleanup(mut commands: Commands, entities: Query<(Entity, &EndingScene)>) {
+    for (entity, _) in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn credits_setup(mut commands: Commands
+    , locale: Locale
+    , fonts: Res<Fonts>)
+{
+    let start = -DESIGN_HEIGHT/2. - CREDITS_ROW_HEIGHT;
+    for (index, key) in CREDITS.iter().enumerate() {
+        let line = Localized::new(*key, if index == 0 { 36. } else { 22. });
+        let (text, effects) = line.text(&locale, &fonts);
+        commands.spawn_bundle(Text2dBundle {
+            text: text.with_alignment(TextAlignment::CENTER),
+            ..default()
+        }).insert(CreditsScene)
+            .insert(Anchored::center(0., start - index as f32*CREDITS_ROW_HEIGHT, 1.))
+            .insert(line)
+            .insert(effects);
+    }
+}
+
+// Rolls the credits up the screen and heads back to the title once they are
+// gone, or sooner if the player asks.
+pub fn credits_roll(mut actions: ResMut<Actions>
+    , mut lines: Query<&mut Anchored, With<CreditsScene>>
+    , game_state: Res<State<GameState>>
+    , mut transition: ResMut<Transition>
+    , time: Res<Time>)
+{
+    let skip = actions.take(Action::Advance) || actions.take(Action::Confirm) || actions.take(Action::Back);
+    actions.clear();
+    if lines.is_empty() {
+        return;
+    }
+
+    let mut showing = false;
+    for mut place in lines.iter_mut() {
+        place.offset.y += CREDITS_SPEED*time.delta_seconds();
+        showing |= place.offset.y < DESIGN_HEIGHT/2. + CREDITS_ROW_HEIGHT;
+    }
+    if skip || !showing {
+        transition.start(game_state.current(), GameState::Title);
+    }
+}
+
+pub fn credits_cleanup(mut commands: Commands, entities: Query<(Entity, &CreditsScene)>) {
+    for (entity, _) in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[derive(Component)]
+pub struct EndingScene;
+
+#[derive(Component)]
+pub struct CreditsScene;
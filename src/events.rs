@@ -0,0 +1,45 @@
+use crate::cooking::CookingSelection;
+use crate::customers::Flavor;
+
+// What happens in the shop, sent by the scene where it happened. The state it
+// changes is updated by systems reading these too, so sounds, achievements
+// and the like can follow along without the scenes knowing about them.
+
+// An ingredient was picked in the kitchen, by its number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IngredientSelected(pub u32);
+
+// The player settled on the current pair of ingredients.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DishCooked {
+    pub selection: CookingSelection,
+    pub flavor: Flavor
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DishServed {
+    pub customer: String,
+    pub selection: CookingSelection
+}
+
+// How the customer took the dish and, if their story just reached one, its outcome.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomerReacted {
+    pub customer: String,
+    pub delighted: bool,
+    pub outcome: Option<String>
+}
+
+// The shop closed for the day, with the money made so far this run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DayEnded {
+    pub day: u32,
+    pub money: u32
+}
+
+// A dialogue line appeared, by its string id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineShown {
+    pub customer: String,
+    pub line: String
+}
@@ -0,0 +1,194 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::input::gamepad::{Gamepad, GamepadAxisType, GamepadButtonType, GamepadEvent, GamepadEventType};
+use bevy::prelude::*;
+
+use crate::actions::Action;
+
+// Buttons report how far they are pressed; past this they count as pressed.
+const BUTTON_THRESHOLD: f32 = 0.75;
+// How far a stick must be pushed before it moves the cursor.
+const STICK_THRESHOLD: f32 = 0.5;
+
+fn button_actions(button: GamepadButtonType) -> &'static [Action] {
+    match button {
+        GamepadButtonType::South => &[Action::Select, Action::Advance],
+        GamepadButtonType::East => &[Action::Back],
+        GamepadButtonType::North => &[Action::Confirm],
+        GamepadButtonType::Start => &[Action::Pause],
+        GamepadButtonType::Select => &[Action::ToggleAuto],
+        GamepadButtonType::LeftTrigger => &[Action::ToggleSkip],
+        GamepadButtonType::DPadUp => &[Action::Up],
+        GamepadButtonType::DPadDown => &[Action::Down],
+        GamepadButtonType::DPadLeft => &[Action::Left],
+        GamepadButtonType::DPadRight => &[Action::Right],
+        _ => &[]
+    }
+}
+
+// The actions for pushing an axis towards its negative and positive ends.
+fn axis_actions(axis: GamepadAxisType) -> Option<(Action, Action)> {
+    match axis {
+        GamepadAxisType::LeftStickX => Some((Action::Left, Action::Right)),
+        GamepadAxisType::LeftStickY => Some((Action::Down, Action::Up)),
+        _ => None
+    }
+}
+
+// Turns raw gamepad events into actions. Fed straight from `GamepadEvent`s so any
+// number of pads can come and go while the game runs.
+#[derive(Debug, Clone, Default)]
+pub struct GamepadInput {
+    held: HashSet<(Gamepad, GamepadButtonType)>,
+    tilted: HashMap<(Gamepad, GamepadAxisType), i8>
+}
+
+impl GamepadInput {
+    pub fn handle(&mut self, event: &GamepadEvent) -> Vec<Action> {
+        let gamepad = event.gamepad;
+        match event.event_type {
+            GamepadEventType::Connected => {
+                info!("Gamepad {} connected", gamepad.id);
+                vec![]
+            }
+            GamepadEventType::Disconnected => {
+                // Forget whatever it was holding so a replugged pad starts fresh.
+                info!("Gamepad {} disconnected", gamepad.id);
+                self.held.retain(|(held, _)| *held != gamepad);
+                self.tilted.retain(|(tilted, _), _| *tilted != gamepad);
+                vec![]
+            }
+            GamepadEventType::ButtonChanged(button, value) => {
+                // Acts on the press, once, like a key does on release.
+                if value < BUTTON_THRESHOLD {
+                    self.held.remove(&(gamepad, button));
+                    vec![]
+                } else if self.held.insert((gamepad, button)) {
+                    button_actions(button).to_vec()
+                } else {
+                    vec![]
+                }
+            }
+            GamepadEventType::AxisChanged(axis, value) => {
+                let (negative, positive) = match axis_actions(axis) {
+                    Some(actions) => actions,
+                    None => return vec![]
+                };
+                let direction = if value >= STICK_THRESHOLD {
+                    1
+                } else if value <= -STICK_THRESHOLD {
+                    -1
+                } else {
+                    0
+                };
+                let previous = self.tilted.insert((gamepad, axis), direction).unwrap_or(0);
+                match direction {
+                    _ if direction == previous => vec![],
+                    1 => vec![positive],
+                    -1 => vec![negative],
+                    _ => vec![]
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::{collect_actions, Actions, Bindings};
+    use crate::common::GameState;
+    use crate::transition::Transition;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_event::<GamepadEvent>()
+            .insert_resource(Input::<KeyCode>::default())
+            .insert_resource(Bindings::default())
+            .insert_resource(Transition::default())
+            .insert_resource(Actions::default())
+            .insert_resource(GamepadInput::default())
+            .add_system(collect_actions);
+        app
+    }
+
+    // Runs a frame with the event and returns the actions it made.
+    fn send(app: &mut App, id: usize, event_type: GamepadEventType) -> Vec<Action> {
+        app.world.resource_mut::<Events<GamepadEvent>>().send(GamepadEvent { gamepad: Gamepad { id }, event_type });
+        app.update();
+        let mut actions = app.world.resource_mut::<Actions>();
+        Action::ALL.into_iter().filter(|action| actions.take(*action)).collect()
+    }
+
+    fn press(button: GamepadButtonType) -> GamepadEventType {
+        GamepadEventType::ButtonChanged(button, 1.)
+    }
+
+    fn release(button: GamepadButtonType) -> GamepadEventType {
+        GamepadEventType::ButtonChanged(button, 0.)
+    }
+
+    #[test]
+    fn buttons_act_once_per_press() {
+        let mut app = app();
+        assert_eq!(send(&mut app, 0, GamepadEventType::Connected), vec![]);
+        assert_eq!(send(&mut app, 0, press(GamepadButtonType::South)), vec![Action::Advance, Action::Select]);
+        // Held down, reported again as it wobbles past the threshold.
+        assert_eq!(send(&mut app, 0, GamepadEventType::ButtonChanged(GamepadButtonType::South, 0.9)), vec![]);
+        assert_eq!(send(&mut app, 0, press(GamepadButtonType::South)), vec![]);
+        assert_eq!(send(&mut app, 0, release(GamepadButtonType::South)), vec![]);
+        assert_eq!(send(&mut app, 0, press(GamepadButtonType::South)), vec![Action::Advance, Action::Select]);
+        assert_eq!(send(&mut app, 0, press(GamepadButtonType::Start)), vec![Action::Pause]);
+    }
+
+    #[test]
+    fn light_presses_and_unmapped_buttons_do_nothing() {
+        let mut app = app();
+        assert_eq!(send(&mut app, 0, GamepadEventType::ButtonChanged(GamepadButtonType::East, 0.5)), vec![]);
+        assert_eq!(send(&mut app, 0, press(GamepadButtonType::RightThumb)), vec![]);
+    }
+
+    #[test]
+    fn sticks_act_when_pushed_past_the_threshold() {
+        let mut app = app();
+        let stick = |value| GamepadEventType::AxisChanged(GamepadAxisType::LeftStickX, value);
+        assert_eq!(send(&mut app, 0, stick(0.3)), vec![]);
+        assert_eq!(send(&mut app, 0, stick(0.8)), vec![Action::Right]);
+        assert_eq!(send(&mut app, 0, stick(1.)), vec![]);
+        assert_eq!(send(&mut app, 0, stick(0.1)), vec![]);
+        assert_eq!(send(&mut app, 0, stick(0.8)), vec![Action::Right]);
+        // Straight across to the other side.
+        assert_eq!(send(&mut app, 0, stick(-0.9)), vec![Action::Left]);
+        assert_eq!(send(&mut app, 0, GamepadEventType::AxisChanged(GamepadAxisType::LeftStickY, 0.7)), vec![Action::Up]);
+        assert_eq!(send(&mut app, 0, GamepadEventType::AxisChanged(GamepadAxisType::RightStickX, 1.)), vec![]);
+    }
+
+    #[test]
+    fn pads_are_tracked_separately() {
+        let mut app = app();
+        assert_eq!(send(&mut app, 0, press(GamepadButtonType::North)), vec![Action::Confirm]);
+        assert_eq!(send(&mut app, 1, press(GamepadButtonType::North)), vec![Action::Confirm]);
+    }
+
+    #[test]
+    fn disconnecting_forgets_held_input() {
+        let mut app = app();
+        send(&mut app, 0, GamepadEventType::Connected);
+        assert_eq!(send(&mut app, 0, press(GamepadButtonType::North)), vec![Action::Confirm]);
+        assert_eq!(send(&mut app, 0, GamepadEventType::AxisChanged(GamepadAxisType::LeftStickY, -1.)), vec![Action::Down]);
+        assert_eq!(send(&mut app, 0, GamepadEventType::Disconnected), vec![]);
+        assert!(app.world.resource::<GamepadInput>().held.is_empty());
+        assert!(app.world.resource::<GamepadInput>().tilted.is_empty());
+        send(&mut app, 0, GamepadEventType::Connected);
+        assert_eq!(send(&mut app, 0, press(GamepadButtonType::North)), vec![Action::Confirm]);
+        assert_eq!(send(&mut app, 0, GamepadEventType::AxisChanged(GamepadAxisType::LeftStickY, -1.)), vec![Action::Down]);
+    }
+
+    #[test]
+    fn nothing_acts_during_a_transition() {
+        let mut app = app();
+        app.world.resource_mut::<Transition>().start(&GameState::Title, GameState::Dialogue);
+        assert_eq!(send(&mut app, 0, press(GamepadButtonType::North)), vec![]);
+    }
+}
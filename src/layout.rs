@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+use bevy::window::{WindowMode, WindowResized};
+
+use crate::actions::{Action, Actions};
+
+// Scenes are authored against this window size and scaled to whatever the window is.
+pub const DESIGN_WIDTH: f32 = 1080.;
+pub const DESIGN_HEIGHT: f32 = 720.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fit {
+    // Scale with the smaller side so the whole element stays visible.
+    Contain,
+    // Scale with the larger side so the element fills the window, for backgrounds.
+    Cover
+}
+
+// Places an entity relative to the window. `anchor` is a point of the window from
+// (-0.5, -0.5) at the bottom left to (0.5, 0.5) at the top right, and `offset` is
+// measured from it in design pixels.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Anchored {
+    pub anchor: Vec2,
+    pub offset: Vec3,
+    pub scale: Vec3,
+    pub fit: Fit
+}
+
+impl Anchored {
+    pub fn new(anchor: Vec2, x: f32, y: f32, z: f32) -> Self {
+        Anchored {
+            anchor,
+            offset: Vec3::new(x, y, z),
+            scale: Vec3::ONE,
+            fit: Fit::Contain
+        }
+    }
+
+    pub fn center(x: f32, y: f32, z: f32) -> Self {
+        Anchored::new(Vec2::ZERO, x, y, z)
+    }
+
+    pub fn left(x: f32, y: f32, z: f32) -> Self {
+        Anchored::new(Vec2::new(-0.5, 0.), x, y, z)
+    }
+
+    pub fn right(x: f32, y: f32, z: f32) -> Self {
+        Anchored::new(Vec2::new(0.5, 0.), x, y, z)
+    }
+
+    pub fn bottom(x: f32, y: f32, z: f32) -> Self {
+        Anchored::new(Vec2::new(0., -0.5), x, y, z)
+    }
+
+    pub fn with_scale(mut self, scale: Vec3) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn covering(mut self) -> Self {
+        self.fit = Fit::Cover;
+        self
+    }
+
+    pub fn transform(&self, width: f32, height: f32) -> Transform {
+        let (horizontal, vertical) = (width/DESIGN_WIDTH, height/DESIGN_HEIGHT);
+        let factor = match self.fit {
+            Fit::Contain => horizontal.min(vertical),
+            Fit::Cover => horizontal.max(vertical)
+        };
+        let translation = Vec3::new(
+            self.anchor.x*width + self.offset.x*factor,
+            self.anchor.y*height + self.offset.y*factor,
+            self.offset.z);
+        Transform::from_translation(translation)
+            .with_scale(self.scale * Vec3::new(factor, factor, 1.))
+    }
+}
+
+// Runs after the scenes spawn their entities and before transforms propagate, so
+// new entities are placed on the frame they appear.
+pub fn layout(windows: Res<Windows>
+    , mut resized: EventReader<WindowResized>
+    , mut anchored: Query<(&Anchored, ChangeTrackers<Anchored>, &mut Transform)>)
+{
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return
+    };
+    let relayout = resized.iter().count() > 0;
+    for (anchored, tracker, mut transform) in anchored.iter_mut() {
+        if relayout || tracker.is_changed() {
+            *transform = anchored.transform(window.width(), window.height());
+        }
+    }
+}
+
+pub fn toggle_fullscreen(mut actions: ResMut<Actions>, mut windows: ResMut<Windows>) {
+    if actions.take(Action::ToggleFullscreen) {
+        switch_fullscreen(&mut windows);
+    }
+}
+
+pub fn switch_fullscreen(windows: &mut Windows) {
+    if let Some(window) = windows.get_primary_mut() {
+        let mode = match window.mode() {
+            WindowMode::Windowed => WindowMode::BorderlessFullscreen,
+            _ => WindowMode::Windowed
+        };
+        window.set_mode(mode);
+    }
+}
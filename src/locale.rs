@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+
+use crate::actions::{Action, Actions};
+use crate::common::*;
+use crate::markup::{self, TextEffects};
+use crate::mods::Mods;
+use crate::storage;
+
+pub const LANGUAGES: &[&str] = &["en", "es"];
+pub const FALLBACK: &str = "en";
+
+// Player-visible strings of one language, loaded from `locale/<language>.strings.ron`.
+#[derive(Debug, Clone, Default, TypeUuid)]
+#[uuid = "5b1f2c8e-3d4a-4f6b-9a7e-1c2d3e4f5a6b"]
+pub struct StringTable(pub HashMap<String, String>);
+
+#[derive(Default)]
+pub struct StringTableLoader;
+
+impl AssetLoader for StringTableLoader {
+    fn load<'a>(&'a self
+        , bytes: &'a [u8]
+        , load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), anyhow::Error>>
+    {
+        Box::pin(async move {
+            let strings: HashMap<String, String> = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(StringTable(strings)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["strings.ron"]
+    }
+}
+
+pub struct Localization {
+    pub language: String,
+    tables: HashMap<String, Handle<StringTable>>,
+    // Tables added by mods, by language, in load order.
+    mod_tables: Vec<(String, Handle<StringTable>)>
+}
+
+impl Localization {
+    pub fn load(asset_server: &AssetServer) -> Self {
+        let tables = LANGUAGES.iter()
+            .map(|language| (language.to_string(), asset_server.load(&format!("locale/{}.strings.ron", language))))
+            .collect();
+        let language: String = storage::load("language");
+        let language = if LANGUAGES.contains(&language.as_str()) { language } else { FALLBACK.to_string() };
+        Localization { language, tables, mod_tables: Vec::new() }
+    }
+
+    pub fn add_mods(&mut self, mods: &Mods) {
+        self.mod_tables = mods.enabled()
+            .flat_map(|installed| installed.strings.iter().cloned())
+            .collect();
+    }
+
+    pub fn tables(&self) -> impl Iterator<Item = &Handle<StringTable>> {
+        self.tables.values()
+    }
+
+    pub fn next_language(&mut self) {
+        let current = LANGUAGES.iter().position(|language| *language == self.language).unwrap_or(0);
+        self.language = LANGUAGES[(current + 1) % LANGUAGES.len()].to_string();
+        storage::store("language", &self.language);
+    }
+}
+
+#[derive(SystemParam)]
+pub struct Locale<'w, 's> {
+    localization: Res<'w, Localization>,
+    tables: Res<'w, Assets<StringTable>>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>
+}
+
+impl<'w, 's> Locale<'w, 's> {
+    // Mods loaded later win over earlier ones and over the game's own strings.
+    fn lookup(&self, language: &str, key: &str) -> Option<&str> {
+        self.localization.mod_tables.iter().rev()
+            .filter(|(table_language, _)| table_language == language)
+            .map(|(_, handle)| handle)
+            .chain(self.localization.tables.get(language))
+            .filter_map(|handle| self.tables.get(handle))
+            .find_map(|StringTable(strings)| strings.get(key))
+            .map(|value| value.as_str())
+    }
+
+    // Falls back to the fallback language, and then to the key itself so a
+    // missing string is visible rather than blank.
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.lookup(&self.localization.language, key)
+            .or_else(|| self.lookup(FALLBACK, key))
+            .unwrap_or(key)
+    }
+
+    pub fn format(&self, key: &str, args: &[(String, String)]) -> String {
+        let mut text = self.get(key).to_string();
+        for (name, value) in args {
+            text = text.replace(&format!("{{{}}}", name), value);
+        }
+        text
+    }
+
+    pub fn language(&self) -> &str {
+        &self.localization.language
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.localization.is_changed()
+    }
+}
+
+// A text entity whose contents come from the string table and follow the language.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Localized {
+    pub key: String,
+    pub args: Vec<(String, String)>,
+    pub font_size: f32,
+    pub color: Color
+}
+
+impl Localized {
+    pub fn new(key: impl Into<String>, font_size: f32) -> Self {
+        Localized {
+            key: key.into(),
+            args: Vec::new(),
+            font_size,
+            color: Color::WHITE
+        }
+    }
+
+    pub fn with_arg(mut self, name: &str, value: impl ToString) -> Self {
+        self.args.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn text(&self, locale: &Locale, fonts: &Fonts) -> (Text, TextEffects) {
+        markup::text(&locale.format(&self.key, &self.args), fonts, self.font_size, self.color)
+    }
+}
+
+pub fn localize(locale: Locale
+    , fonts: Res<Fonts>
+    , mut table_events: EventReader<AssetEvent<StringTable>>
+    , mut texts: Query<(&Localized, ChangeTrackers<Localized>, &mut Text, Option<&mut TextEffects>)>)
+{
+    let refresh = locale.is_changed() || table_events.iter().count() > 0;
+    for (localized, tracker, mut text, effects) in texts.iter_mut() {
+        if !refresh && !tracker.is_changed() {
+            continue;
+        }
+        let (new_text, new_effects) = localized.text(&locale, &fonts);
+        let alignment = text.alignment;
+        *text = new_text.with_alignment(alignment);
+        if let Some(mut effects) = effects {
+            *effects = new_effects;
+        }
+    }
+}
+
+pub fn switch_language(mut actions: ResMut<Actions>, mut localization: ResMut<Localization>) {
+    if actions.take(Action::SwitchLanguage) {
+        localization.next_language();
+    }
+}
+
+// `--check-locales`: lists the keys each language is missing compared to the fallback.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn check() -> bool {
+    let read = |language: &str| -> Result<HashMap<String, String>, String> {
+        let path = format!("assets/locale/{}.strings.ron", language);
+        let contents = std::fs::read_to_string(&path).map_err(|error| format!("{}: {}", path, error))?;
+        ron::de::from_str(&contents).map_err(|error| format!("{}: {}", path, error))
+    };
+
+    let fallback = match read(FALLBACK) {
+        Ok(fallback) => fallback,
+        Err(error) => {
+            println!("{}", error);
+            return false;
+        }
+    };
+    let mut complete = true;
+    for language in LANGUAGES.iter().filter(|language| **language != FALLBACK) {
+        let strings = match read(language) {
+            Ok(strings) => strings,
+            Err(error) => {
+                println!("{}", error);
+                complete = false;
+                continue;
+            }
+        };
+        let mut missing: Vec<&String> = fallback.keys().filter(|key| !strings.contains_key(*key)).collect();
+        let mut unused: Vec<&String> = strings.keys().filter(|key| !fallback.contains_key(*key)).collect();
+        missing.sort();
+        unused.sort();
+        complete = complete && missing.is_empty();
+        println!("{}: {} missing, {} unknown", language, missing.len(), unused.len());
+        for key in missing {
+            println!("  missing {}", key);
+        }
+        for key in unused {
+            println!("  unknown {}", key);
+        }
+    }
+    complete
+}
@@ -1,56 +1,323 @@
 
+use bevy::input::InputSystem;
 use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use bevy::window::WindowResizeConstraints;
 
+mod achievements;
+mod actions;
+mod arcs;
+mod audio;
+mod book;
+mod calendar;
+mod campaign;
 mod common;
-mod dialogue;
+#[cfg(feature = "console")]
+mod console;
+mod controls;
 mod cooking;
+mod customers;
+mod dialogue;
+mod ending;
+mod events;
+mod gamepad;
+mod layout;
+mod locale;
+mod markup;
+mod menu;
+mod mods;
+mod pause;
+mod recipes;
+mod reputation;
+#[cfg(not(target_arch = "wasm32"))]
+mod replay;
+mod rng;
+mod save;
+mod settings;
+mod stats;
+mod storage;
+mod title;
+mod transition;
 
+use achievements::*;
+use actions::*;
+use audio::*;
+use book::*;
+use calendar::*;
+use campaign::Campaign;
 use common::*;
-use dialogue::*;
+use controls::*;
 use cooking::*;
+use customers::*;
+use dialogue::*;
+use ending::*;
+use events::*;
+use gamepad::GamepadInput;
+use layout::*;
+use locale::*;
+use markup::text_effects;
+use menu::menu_highlight;
+use mods::*;
+use pause::*;
+use recipes::{Ingredients, IngredientsHandle, IngredientsLoader, Recipes, RecipesHandle, RecipesLoader};
+use reputation::Reputation;
+use rng::{GameRng, Seed};
+use save::{autosave, Progress};
+use settings::*;
+use stats::*;
+use title::*;
+use transition::*;
 
 fn main() {
-    App::new()
-        .insert_resource(WindowDescriptor {
-            width: 1080.,
-            height: 720.,
-            resizable: false,
+    #[cfg(not(target_arch = "wasm32"))]
+    if std::env::args().any(|arg| arg == "--check-locales") {
+        std::process::exit(if locale::check() { 0 } else { 1 });
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = std::env::args().skip_while(|arg| arg != "--export-stats").nth(1) {
+        std::process::exit(if stats::export(&path) { 0 } else { 1 });
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = std::env::args().skip_while(|arg| arg != "--replay").nth(1) {
+        replay::run(&path);
+    }
+
+    let mut app = App::new();
+    #[cfg(feature = "hot_reload")]
+    app.insert_resource(bevy::asset::AssetServerSettings {
+        watch_for_changes: true,
+        ..default()
+    });
+    app.insert_resource(WindowDescriptor {
+            width: DESIGN_WIDTH,
+            height: DESIGN_HEIGHT,
+            resizable: true,
+            resize_constraints: WindowResizeConstraints {
+                min_width: DESIGN_WIDTH/2.,
+                min_height: DESIGN_HEIGHT/2.,
+                ..default()
+            },
             present_mode: bevy::window::PresentMode::AutoVsync,
             ..default()
         })
-        .add_state(GameState::Dialogue)
-        .insert_resource(DialogueState(0, 0))
+        .add_plugins(DefaultPlugins);
+    add_game(&mut app);
+    app.add_system_to_stage(CoreStage::PreUpdate, collect_actions.label(ActionSystem).after(InputSystem))
+        .add_system_to_stage(CoreStage::PreUpdate, toggle_fullscreen.after(ActionSystem))
+        .add_system_to_stage(CoreStage::PostUpdate, state_music)
+        .add_system_to_stage(CoreStage::PostUpdate, play_audio)
+        .add_system_to_stage(CoreStage::PostUpdate, layout.before(TransformSystem::TransformPropagate));
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = std::env::args().skip_while(|arg| arg != "--record").nth(1) {
+        app.insert_resource(replay::Recorder::new(&path))
+            .add_system_to_stage(CoreStage::PreUpdate, replay::record_actions.label(ActionSystem).after(collect_actions))
+            .add_system_to_stage(CoreStage::Last, replay::save_recording);
+    }
+    #[cfg(feature = "console")]
+    app.insert_resource(console::Console::default())
+        .add_system_to_stage(CoreStage::PreUpdate, console::console_input.after(InputSystem).before(collect_actions))
+        .add_system(console::run_commands)
+        .add_system(console::console_display);
+    app.run();
+}
+
+// Everything the game itself adds to the engine, shared by the window and the
+// headless replay. Input, sound and the window are left to the caller.
+pub fn add_game(app: &mut App) {
+    app.add_state(GameState::Title)
+        .insert_resource(DialogueState::default())
+        .insert_resource(Visits::default())
+        .insert_resource(Reputation::default())
+        .insert_resource(Campaign::default())
+        .insert_resource(Calendar::default())
+        .insert_resource(Unlocks::load())
+        .insert_resource(GameRng::default())
+        .insert_resource(Seed::load())
+        .insert_resource(DialogueAutoplay::default())
+        .insert_resource(ReadLines::load())
         .insert_resource(CookingSelection(0, 0))
-        .insert_resource(Handle::<Font>::default())
-        .add_plugins(DefaultPlugins)
+        .insert_resource(IngredientCursor::default())
+        .insert_resource(RecipeBook::load())
+        .insert_resource(AchievementRecord::load())
+        .insert_resource(Stats::load())
+        .insert_resource(Bindings::load())
+        .insert_resource(Actions::default())
+        .insert_resource(GamepadInput::default())
+        .insert_resource(Volume::load())
+        .insert_resource(Music::default())
+        .insert_resource(ControlsCursor::default())
+        .insert_resource(Transition::default())
+        .insert_resource(Content::default())
+        .add_asset::<StringTable>()
+        .init_asset_loader::<StringTableLoader>()
+        .add_asset::<Customers>()
+        .init_asset_loader::<CustomersLoader>()
+        .add_asset::<StoryEvents>()
+        .init_asset_loader::<StoryEventsLoader>()
+        .add_asset::<Achievements>()
+        .init_asset_loader::<AchievementsLoader>()
+        .add_asset::<Ingredients>()
+        .init_asset_loader::<IngredientsLoader>()
+        .add_asset::<Recipes>()
+        .init_asset_loader::<RecipesLoader>()
+        .add_asset::<Transitions>()
+        .init_asset_loader::<TransitionsLoader>()
+        .add_event::<AudioRequest>()
+        .add_event::<IngredientSelected>()
+        .add_event::<DishCooked>()
+        .add_event::<DishServed>()
+        .add_event::<CustomerReacted>()
+        .add_event::<DayEnded>()
+        .add_event::<LineShown>()
         .add_startup_system(setup)
+        .add_system_set(SystemSet::on_enter(GameState::Title)
+            .with_system(title_setup))
+        .add_system_set(SystemSet::on_update(GameState::Title)
+            .with_system(title_input))
+        .add_system_set(SystemSet::on_exit(GameState::Title)
+            .with_system(title_cleanup))
         .add_system_set(SystemSet::on_enter(GameState::Dialogue)
-            .with_system(dialogue_setup))
+            .with_system(dialogue_setup)
+            .with_system(autosave))
         .add_system_set(SystemSet::on_update(GameState::Dialogue)
             .with_system(dialogue_text)
+            .with_system(reload_dialogue.before(dialogue_text))
+            .with_system(recover_scene.before(dialogue_text).before(dialogue_next))
+            .with_system(restart_autoplay.before(dialogue_next))
             .with_system(dialogue_next))
         .add_system_set(SystemSet::on_exit(GameState::Dialogue)
             .with_system(dialogue_cleanup))
         .add_system_set(SystemSet::on_enter(GameState::Cooking)
             .with_system(cooking_setup))
         .add_system_set(SystemSet::on_update(GameState::Cooking)
-            .with_system(cook))
+            .with_system(cooking_cursor.before(cook))
+            .with_system(cook)
+            .with_system(select_ingredient.after(cook))
+            .with_system(record_recipe.after(cook))
+            .with_system(serve_dish.after(cook))
+            .with_system(reload_recipes.after(select_ingredient)))
         .add_system_set(SystemSet::on_exit(GameState::Cooking)
             .with_system(cooking_cleanup))
         .add_system_set(SystemSet::on_update(GameState::Intermission)
-            .with_system(intermission))
-        .add_system(bevy::window::close_on_esc)
-        .run();
+            .with_system(intermission)
+            .with_system(end_day.after(intermission)))
+        .add_system_set(SystemSet::on_enter(GameState::Ending)
+            .with_system(ending_setup))
+        .add_system_set(SystemSet::on_update(GameState::Ending)
+            .with_system(ending_input))
+        .add_system_set(SystemSet::on_exit(GameState::Ending)
+            .with_system(ending_cleanup))
+        .add_system_set(SystemSet::on_enter(GameState::Credits)
+            .with_system(credits_setup))
+        .add_system_set(SystemSet::on_update(GameState::Credits)
+            .with_system(credits_roll))
+        .add_system_set(SystemSet::on_exit(GameState::Credits)
+            .with_system(credits_cleanup))
+        .add_system_set(SystemSet::on_enter(GameState::Paused)
+            .with_system(pause_setup))
+        .add_system_set(SystemSet::on_update(GameState::Paused)
+            .with_system(pause_input))
+        .add_system_set(SystemSet::on_exit(GameState::Paused)
+            .with_system(pause_cleanup))
+        .add_system_set(SystemSet::on_enter(GameState::Settings)
+            .with_system(settings_setup))
+        .add_system_set(SystemSet::on_update(GameState::Settings)
+            .with_system(settings_values)
+            .with_system(settings_input))
+        .add_system_set(SystemSet::on_exit(GameState::Settings)
+            .with_system(settings_cleanup))
+        .add_system_set(SystemSet::on_enter(GameState::Controls)
+            .with_system(controls_setup))
+        .add_system_set(SystemSet::on_update(GameState::Controls)
+            .with_system(controls_rows)
+            .with_system(controls_input))
+        .add_system_set(SystemSet::on_exit(GameState::Controls)
+            .with_system(controls_cleanup))
+        .add_system_set(SystemSet::on_enter(GameState::Achievements)
+            .with_system(achievements_setup))
+        .add_system_set(SystemSet::on_update(GameState::Achievements)
+            .with_system(achievements_input))
+        .add_system_set(SystemSet::on_exit(GameState::Achievements)
+            .with_system(achievements_cleanup))
+        .add_system_set(SystemSet::on_enter(GameState::Stats)
+            .with_system(stats_setup))
+        .add_system_set(SystemSet::on_update(GameState::Stats)
+            .with_system(stats_input))
+        .add_system_set(SystemSet::on_exit(GameState::Stats)
+            .with_system(stats_cleanup))
+        .add_system_set(SystemSet::on_enter(GameState::Mods)
+            .with_system(mods_setup))
+        .add_system_set(SystemSet::on_update(GameState::Mods)
+            .with_system(mods_input))
+        .add_system_set(SystemSet::on_exit(GameState::Mods)
+            .with_system(mods_cleanup))
+        .add_system(text_effects)
+        .add_system(menu_highlight)
+        .add_system(track_achievements)
+        .add_system(toast_fade)
+        .add_system(count_stats)
+        .add_system(time_states)
+        .add_system(load_transitions)
+        .add_system(run_transition.after(dialogue_next))
+        .add_system(transition_visuals)
+        .add_system_to_stage(CoreStage::PreUpdate, open_pause.after(ActionSystem))
+        .add_system_to_stage(CoreStage::PreUpdate, switch_language.after(ActionSystem))
+        .add_system_to_stage(CoreStage::PreUpdate, merge_content)
+        .add_system_to_stage(CoreStage::PostUpdate, localize);
 }
 
 fn setup(mut commands: Commands
-    , asset_server: Res<AssetServer>
-    , mut font: ResMut<Handle<Font>>) {
+    , asset_server: Res<AssetServer>) {
     commands.spawn_bundle(Camera2dBundle::default());
-    *font = asset_server.load("fonts/FiraCode-Regular.ttf");
+    commands.insert_resource(Fonts::load(&asset_server));
+    let mods = Mods::scan(&asset_server);
+    let mut localization = Localization::load(&asset_server);
+    localization.add_mods(&mods);
+    commands.insert_resource(localization);
+    commands.insert_resource(mods);
+    commands.insert_resource(CustomersHandle::load(&asset_server));
+    commands.insert_resource(StoryEventsHandle::load(&asset_server));
+    commands.insert_resource(AchievementsHandle::load(&asset_server));
+    commands.insert_resource(IngredientsHandle::load(&asset_server));
+    commands.insert_resource(RecipesHandle::load(&asset_server));
+    commands.insert_resource(TransitionsHandle::load(&asset_server));
+}
+
+// Takes payment and brings in the next customer, unless that was the last
+// one of the day.
+fn intermission(mut progress: Progress
+    , roster: Roster
+    , mut day_ended: EventWriter<DayEnded>
+    , mut game_state: ResMut<State<GameState>>)
+{
+    if progress.campaign.serve(progress.dialogue.part == Part::Delighted) {
+        day_ended.send(DayEnded {
+            day: progress.calendar.day,
+            money: progress.campaign.money
+        });
+        return;
+    }
+    progress.next_customer(roster.archetypes());
+    game_state.set(GameState::Dialogue).ok();
 }
 
-fn intermission(mut dialogue_state: ResMut<DialogueState>, mut game_state: ResMut<State<GameState>>) {
-    dialogue_state.next_scene(0, 0, 0);
+// The screen is covered here, so a finished run can go straight to its ending.
+fn end_day(mut day_ended: EventReader<DayEnded>
+    , mut progress: Progress
+    , roster: Roster
+    , schedule: StorySchedule
+    , mut game_state: ResMut<State<GameState>>)
+{
+    if day_ended.iter().count() == 0 {
+        return;
+    }
+    let archetypes = roster.archetypes();
+    progress.calendar.advance();
+    if progress.calendar.is_over() {
+        game_state.set(GameState::Ending).ok();
+        return;
+    }
+    progress.calendar.start_day(schedule.events(), archetypes, &progress.reputation, &progress.dialogue.arcs);
+    progress.next_customer(archetypes);
     game_state.set(GameState::Dialogue).ok();
 }
@@ -0,0 +1,303 @@
+use bevy::prelude::*;
+
+use crate::common::*;
+
+// A tiny inline markup for dialogue lines and recipe descriptions:
+//   [b]bold[/b], [sb]semi bold[/sb], [m]medium[/m], [i]light[/i],
+//   [color=red]named or #rrggbb colours[/color], [flicker]flickering[/flicker]
+// and "[[" for a literal bracket. Anything that isn't a known tag is kept as text.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weight {
+    Light,
+    Regular,
+    Medium,
+    SemiBold,
+    Bold
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Style {
+    pub weight: Weight,
+    pub color: Option<Color>,
+    pub flicker: bool
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            weight: Weight::Regular,
+            color: None,
+            flicker: false
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Effect {
+    Weight(Weight),
+    Color(Color),
+    Flicker
+}
+
+impl Effect {
+    fn apply(&self, style: &mut Style) {
+        match self {
+            Effect::Weight(weight) => style.weight = *weight,
+            Effect::Color(color) => style.color = Some(*color),
+            Effect::Flicker => style.flicker = true
+        }
+    }
+}
+
+enum Tag<'a> {
+    Open(&'a str, Effect),
+    Close(&'a str)
+}
+
+impl<'a> Tag<'a> {
+    fn parse(tag: &'a str) -> Option<Tag<'a>> {
+        if let Some(name) = tag.strip_prefix('/') {
+            return match name {
+                "b" | "sb" | "m" | "i" | "color" | "flicker" => Some(Tag::Close(name)),
+                _ => None
+            };
+        }
+        let (name, argument) = match tag.split_once('=') {
+            Some((name, argument)) => (name, Some(argument)),
+            None => (tag, None)
+        };
+        let effect = match (name, argument) {
+            ("b", None) => Effect::Weight(Weight::Bold),
+            ("sb", None) => Effect::Weight(Weight::SemiBold),
+            ("m", None) => Effect::Weight(Weight::Medium),
+            // FiraCode has no italics, the light weight stands in for them.
+            ("i", None) => Effect::Weight(Weight::Light),
+            ("color", Some(color)) => Effect::Color(parse_color(color)?),
+            ("flicker", None) => Effect::Flicker,
+            _ => return None
+        };
+        Some(Tag::Open(name, effect))
+    }
+}
+
+fn parse_color(color: &str) -> Option<Color> {
+    match color {
+        "white" => Some(Color::WHITE),
+        "black" => Some(Color::BLACK),
+        "gray" | "grey" => Some(Color::GRAY),
+        "red" => Some(Color::rgb(0.9, 0.2, 0.2)),
+        "green" => Some(Color::rgb(0.4, 0.85, 0.3)),
+        "blue" => Some(Color::rgb(0.4, 0.6, 1.)),
+        "yellow" => Some(Color::rgb(1., 0.9, 0.3)),
+        "orange" => Some(Color::rgb(1., 0.55, 0.1)),
+        "purple" => Some(Color::rgb(0.7, 0.4, 0.9)),
+        _ => color.strip_prefix('#').and_then(|hex| Color::hex(hex).ok())
+    }
+}
+
+fn style_of(stack: &[(&str, Effect)]) -> Style {
+    let mut style = Style::default();
+    for (_, effect) in stack {
+        effect.apply(&mut style);
+    }
+    style
+}
+
+fn flush(spans: &mut Vec<Span>, text: &mut String, style: Style) {
+    if text.is_empty() {
+        return;
+    }
+    match spans.last_mut() {
+        Some(last) if last.style == style => last.text.push_str(text),
+        _ => spans.push(Span { text: text.clone(), style })
+    }
+    text.clear();
+}
+
+pub fn parse(source: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut stack: Vec<(&str, Effect)> = Vec::new();
+    let mut text = String::new();
+    let mut rest = source;
+
+    while let Some(start) = rest.find('[') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("[[") {
+            text.push('[');
+            rest = after;
+            continue;
+        }
+
+        let tag = rest.find(']').and_then(|end| Tag::parse(&rest[1..end]).map(|tag| (tag, end)));
+        match tag {
+            Some((Tag::Open(name, effect), end)) => {
+                flush(&mut spans, &mut text, style_of(&stack));
+                stack.push((name, effect));
+                rest = &rest[end + 1..];
+            }
+            Some((Tag::Close(name), end)) if stack.iter().any(|(open, _)| *open == name) => {
+                flush(&mut spans, &mut text, style_of(&stack));
+                let index = stack.iter().rposition(|(open, _)| *open == name).unwrap();
+                stack.remove(index);
+                rest = &rest[end + 1..];
+            }
+            _ => {
+                text.push('[');
+                rest = &rest[1..];
+            }
+        }
+    }
+    text.push_str(rest);
+    flush(&mut spans, &mut text, style_of(&stack));
+    spans
+}
+
+// The number of characters actually shown once the tags are stripped.
+pub fn length(source: &str) -> usize {
+    parse(source).iter().map(|span| span.text.chars().count()).sum()
+}
+
+// Sections of a markup text that flicker, with the colour they rest at.
+#[derive(Component, Debug, Clone, Default, PartialEq)]
+pub struct TextEffects(pub Vec<(usize, Color)>);
+
+pub fn text(source: &str, fonts: &Fonts, font_size: f32, color: Color) -> (Text, TextEffects) {
+    let mut sections = Vec::new();
+    let mut flickering = Vec::new();
+    for span in parse(source) {
+        let style = TextStyle {
+            font: fonts.weight(span.style.weight),
+            font_size,
+            color: span.style.color.unwrap_or(color)
+        };
+        if span.style.flicker {
+            // Every flickering glyph gets its own section so that each one
+            // fades in and out at its own pace.
+            for glyph in span.text.chars() {
+                flickering.push((sections.len(), style.color));
+                sections.push(TextSection { value: glyph.to_string(), style: style.clone() });
+            }
+        } else {
+            sections.push(TextSection { value: span.text, style });
+        }
+    }
+    (Text { sections, ..default() }, TextEffects(flickering))
+}
+
+pub fn text_effects(time: Res<Time>, mut texts: Query<(&mut Text, &TextEffects)>) {
+    let seconds = time.seconds_since_startup() as f32;
+    for (mut text, TextEffects(flickering)) in texts.iter_mut() {
+        if flickering.is_empty() {
+            continue;
+        }
+        for (index, color) in flickering {
+            if let Some(section) = text.sections.get_mut(*index) {
+                let phase = seconds * 25. + *index as f32 * 1.7;
+                let flicker = 0.65 + 0.35 * phase.sin().abs();
+                let mut color = *color;
+                color.set_a(color.a() * flicker);
+                section.style.color = color;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(text: &str, weight: Weight, color: Option<Color>, flicker: bool) -> Span {
+        Span { text: text.to_string(), style: Style { weight, color, flicker } }
+    }
+
+    fn plain(text: &str) -> Span {
+        span(text, Weight::Regular, None, false)
+    }
+
+    #[test]
+    fn nested_tags_combine() {
+        let red = parse_color("red");
+        assert_eq!(parse("a[b]b[color=red]c[/color]d[/b]e"), vec![
+            plain("a"),
+            span("b", Weight::Bold, None, false),
+            span("c", Weight::Bold, red, false),
+            span("d", Weight::Bold, None, false),
+            plain("e")
+        ]);
+    }
+
+    #[test]
+    fn inner_weight_wins_until_closed() {
+        assert_eq!(parse("[b]a[i]b[/i]c[/b]"), vec![
+            span("a", Weight::Bold, None, false),
+            span("b", Weight::Light, None, false),
+            span("c", Weight::Bold, None, false)
+        ]);
+    }
+
+    #[test]
+    fn unknown_tags_are_text() {
+        assert_eq!(parse("[wave]hi[/wave]"), vec![plain("[wave]hi[/wave]")]);
+        assert_eq!(parse("[color=mauve]hi[/color]"), vec![plain("[color=mauve]hi[/color]")]);
+        assert_eq!(parse("[b=1]hi"), vec![plain("[b=1]hi")]);
+    }
+
+    #[test]
+    fn double_bracket_escapes() {
+        assert_eq!(parse("[[b]not bold"), vec![plain("[b]not bold")]);
+        assert_eq!(parse("a [[ b"), vec![plain("a [ b")]);
+    }
+
+    #[test]
+    fn stray_close_is_text() {
+        assert_eq!(parse("a[/b]c"), vec![plain("a[/b]c")]);
+        assert_eq!(parse("[b]a[/i]b[/b]"), vec![span("a[/i]b", Weight::Bold, None, false)]);
+    }
+
+    #[test]
+    fn unclosed_tag_runs_to_the_end() {
+        assert_eq!(parse("a[b]b"), vec![plain("a"), span("b", Weight::Bold, None, false)]);
+        assert_eq!(parse("a[b"), vec![plain("a[b")]);
+    }
+
+    #[test]
+    fn named_and_hex_colors() {
+        assert_eq!(parse_color("grey"), Some(Color::GRAY));
+        assert_eq!(parse_color("orange"), Some(Color::rgb(1., 0.55, 0.1)));
+        assert_eq!(parse_color("#ff8000"), Color::hex("ff8000").ok());
+        assert_eq!(parse_color("ff8000"), None);
+        assert_eq!(parse_color("#zzzzzz"), None);
+        assert_eq!(parse("[color=#00ff00]x[/color]"), vec![span("x", Weight::Regular, Color::hex("00ff00").ok(), false)]);
+    }
+
+    #[test]
+    fn flicker_gets_a_section_per_glyph() {
+        let fonts = Fonts {
+            light: default(),
+            regular: default(),
+            medium: default(),
+            semi_bold: default(),
+            bold: default()
+        };
+        let (text, TextEffects(flickering)) = text("a[flicker]boo[/flicker]!", &fonts, 16., Color::WHITE);
+        let values: Vec<&str> = text.sections.iter().map(|section| section.value.as_str()).collect();
+        assert_eq!(values, vec!["a", "b", "o", "o", "!"]);
+        assert_eq!(flickering, vec![(1, Color::WHITE), (2, Color::WHITE), (3, Color::WHITE)]);
+    }
+
+    #[test]
+    fn length_ignores_markup() {
+        assert_eq!(length("[b]bold[/b] [color=red]red[/color]"), 8);
+        assert_eq!(length("[[b]"), 3);
+        assert_eq!(length("[wave]"), 6);
+        assert_eq!(length("¡[i]olé[/i]!"), 5);
+    }
+}
@@ -0,0 +1,117 @@
+use bevy::prelude::*;
+
+use crate::actions::{Action, Actions};
+use crate::common::*;
+use crate::layout::Anchored;
+use crate::locale::{Locale, Localized};
+
+const ENTRY_HEIGHT: f32 = 40.;
+const SELECTED: Color = Color::rgb(1., 0.85, 0.3);
+const DISABLED: Color = Color::rgb(0.45, 0.45, 0.45);
+
+// A vertical list of entries, walked with Up/Down and picked with Confirm.
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct Menu {
+    pub selected: usize,
+    pub enabled: Vec<bool>
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MenuEntry {
+    pub menu: Entity,
+    pub index: usize
+}
+
+impl Menu {
+    pub fn new(enabled: Vec<bool>) -> Self {
+        let selected = enabled.iter().position(|enabled| *enabled).unwrap_or(0);
+        Menu { selected, enabled }
+    }
+
+    // Moves the selection, skipping disabled entries, and returns the entry
+    // picked this frame if there is one.
+    pub fn navigate(&mut self, actions: &mut Actions) -> Option<usize> {
+        let entries = self.enabled.len();
+        if entries == 0 {
+            return None;
+        }
+        if actions.take(Action::Up) {
+            self.step(entries - 1);
+        }
+        if actions.take(Action::Down) {
+            self.step(1);
+        }
+        let picked = actions.take(Action::Confirm) | actions.take(Action::Select) | actions.take(Action::Advance);
+        if picked && self.enabled[self.selected] { Some(self.selected) } else { None }
+    }
+
+    fn step(&mut self, offset: usize) {
+        let entries = self.enabled.len();
+        for _ in 0..entries {
+            self.selected = (self.selected + offset) % entries;
+            if self.enabled[self.selected] {
+                break;
+            }
+        }
+    }
+}
+
+// Spawns a titled menu centred on the window. Everything is tagged with `scene`
+// so the screen's cleanup removes it along with the rest.
+pub fn spawn_menu<S: Component + Copy>(commands: &mut Commands
+    , scene: S
+    , z: f32
+    , title: &str
+    , entries: &[(&str, bool)]
+    , locale: &Locale
+    , fonts: &Fonts) -> Entity
+{
+    let menu = commands.spawn()
+        .insert(scene)
+        .insert(Menu::new(entries.iter().map(|(_, enabled)| *enabled).collect()))
+        .id();
+
+    let top = entries.len() as f32*ENTRY_HEIGHT/2.;
+    let title = Localized::new(title, 36.);
+    let (text, effects) = title.text(locale, fonts);
+    commands.spawn_bundle(Text2dBundle {
+        text: text.with_alignment(TextAlignment::CENTER),
+        ..default()
+    }).insert(scene)
+        .insert(Anchored::center(0., top + 2.*ENTRY_HEIGHT, z))
+        .insert(title)
+        .insert(effects);
+
+    for (index, (key, _)) in entries.iter().enumerate() {
+        let entry = Localized::new(*key, 24.);
+        let (text, effects) = entry.text(locale, fonts);
+        commands.spawn_bundle(Text2dBundle {
+            text: text.with_alignment(TextAlignment::CENTER),
+            ..default()
+        }).insert(scene)
+            .insert(Anchored::center(0., top - index as f32*ENTRY_HEIGHT, z))
+            .insert(MenuEntry { menu, index })
+            .insert(entry)
+            .insert(effects);
+    }
+    menu
+}
+
+pub fn menu_highlight(menus: Query<&Menu>, mut entries: Query<(&MenuEntry, &mut Localized)>) {
+    for (entry, mut localized) in entries.iter_mut() {
+        let menu = match menus.get(entry.menu) {
+            Ok(menu) => menu,
+            Err(_) => continue
+        };
+        let color = if menu.selected == entry.index {
+            SELECTED
+        } else if menu.enabled.get(entry.index) == Some(&true) {
+            Color::WHITE
+        } else {
+            DISABLED
+        };
+        if localized.color != color {
+            localized.color = color;
+        }
+    }
+}
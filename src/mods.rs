@@ -0,0 +1,509 @@
+use std::collections::BTreeMap;
+
+use bevy::asset::{HandleId, LoadState};
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::actions::{Action, Actions};
+use crate::common::*;
+use crate::cooking::MAX_INGREDIENTS;
+use crate::customers::{Archetype, Customers, CustomersHandle};
+use crate::layout::{Anchored, DESIGN_HEIGHT, DESIGN_WIDTH};
+use crate::locale::{Locale, Localized, StringTable};
+use crate::recipes::{Ingredient, Ingredients, IngredientsHandle, Recipe, Recipes, RecipesHandle};
+
+// Next to the assets folder, so files in it load as `../mods/<folder>/<file>`.
+#[cfg(not(target_arch = "wasm32"))]
+const DIRECTORY: &str = "mods";
+const ROW_HEIGHT: f32 = 24.;
+// As many lines as fit on the mod list.
+const MAX_LINES: usize = 20;
+const PROBLEM: Color = Color::rgb(1., 0.45, 0.4);
+const NOTE: Color = Color::rgb(0.6, 0.6, 0.6);
+
+// `mods/<folder>/mod.ron`. Files are named from the mod's folder, but art
+// inside them is named from the assets folder, as `../mods/<folder>/...`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Manifest {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    // Mods to load before this one, when they're installed.
+    #[serde(default)]
+    pub after: Vec<String>,
+    #[serde(default)]
+    pub customers: Option<String>,
+    #[serde(default)]
+    pub ingredients: Option<String>,
+    #[serde(default)]
+    pub recipes: Option<String>,
+    // String tables by language.
+    #[serde(default)]
+    pub strings: BTreeMap<String, String>
+}
+
+// Something the mod list tells players about a mod. `origin` is the name of
+// the mod that had what was replaced, or none for the game itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Note {
+    Unreadable(String),
+    DuplicateId { folder: String, id: String },
+    CircularOrder,
+    NotLoaded(String),
+    ReplacesCustomer { id: String, origin: Option<String> },
+    ReplacesIngredient { id: u32, origin: Option<String> },
+    ReplacesRecipe { ingredients: (u32, u32), origin: Option<String> },
+    ZeroIngredient,
+    KitchenFull(u32),
+    UnknownIngredient { ingredients: (u32, u32), missing: u32 }
+}
+
+impl Note {
+    pub fn localized(&self) -> Localized {
+        let replaces = |key: &str, origin: &Option<String>| match origin {
+            Some(origin) => Localized::new(format!("mods.replaces_{}", key), 14.).with_arg("origin", origin),
+            None => Localized::new(format!("mods.replaces_game_{}", key), 14.)
+        };
+        match self {
+            Note::Unreadable(error) => Localized::new("mods.unreadable", 14.).with_arg("error", error),
+            Note::DuplicateId { folder, id } => Localized::new("mods.duplicate_id", 14.)
+                .with_arg("folder", folder)
+                .with_arg("id", id),
+            Note::CircularOrder => Localized::new("mods.circular_order", 14.),
+            Note::NotLoaded(file) => Localized::new("mods.not_loaded", 14.).with_arg("file", file),
+            Note::ReplacesCustomer { id, origin } => replaces("customer", origin).with_arg("id", id),
+            Note::ReplacesIngredient { id, origin } => replaces("ingredient", origin).with_arg("id", id),
+            Note::ReplacesRecipe { ingredients: (first, second), origin } => replaces("recipe", origin)
+                .with_arg("first", first)
+                .with_arg("second", second),
+            Note::ZeroIngredient => Localized::new("mods.zero_ingredient", 14.),
+            Note::KitchenFull(id) => Localized::new("mods.kitchen_full", 14.)
+                .with_arg("id", id)
+                .with_arg("count", MAX_INGREDIENTS),
+            Note::UnknownIngredient { ingredients: (first, second), missing } => Localized::new("mods.unknown_ingredient", 14.)
+                .with_arg("first", first)
+                .with_arg("second", second)
+                .with_arg("missing", missing)
+        }
+    }
+}
+
+// One installed mod and how loading it went. Disabled mods load nothing.
+#[derive(Debug, Clone, Default)]
+pub struct Mod {
+    pub folder: String,
+    pub manifest: Option<Manifest>,
+    pub enabled: bool,
+    pub customers: Option<Handle<Customers>>,
+    pub ingredients: Option<Handle<Ingredients>>,
+    pub recipes: Option<Handle<Recipes>>,
+    pub strings: Vec<(String, Handle<StringTable>)>,
+    pub problems: Vec<Note>,
+    // What the mod replaced or skipped, worked out again whenever the content changes.
+    pub notes: Vec<Note>
+}
+
+impl Mod {
+    pub fn name(&self) -> &str {
+        self.manifest.as_ref()
+            .map(|manifest| manifest.name.as_str())
+            .unwrap_or(&self.folder)
+    }
+
+    fn handles(&self) -> impl Iterator<Item = (String, HandleId)> + '_ {
+        let manifest = self.manifest.as_ref();
+        let customers = self.customers.iter()
+            .map(move |handle| (manifest.and_then(|manifest| manifest.customers.clone()).unwrap_or_default(), HandleId::from(handle)));
+        let ingredients = self.ingredients.iter()
+            .map(move |handle| (manifest.and_then(|manifest| manifest.ingredients.clone()).unwrap_or_default(), HandleId::from(handle)));
+        let recipes = self.recipes.iter()
+            .map(move |handle| (manifest.and_then(|manifest| manifest.recipes.clone()).unwrap_or_default(), HandleId::from(handle)));
+        let strings = self.strings.iter()
+            .map(move |(language, handle)| {
+                let file = manifest.and_then(|manifest| manifest.strings.get(language).cloned()).unwrap_or_default();
+                (file, HandleId::from(handle))
+            });
+        customers.chain(ingredients).chain(recipes).chain(strings)
+    }
+}
+
+// Every installed mod, enabled ones first in load order.
+#[derive(Debug, Clone, Default)]
+pub struct Mods(pub Vec<Mod>);
+
+impl Mods {
+    // Reads every manifest and starts loading what the enabled mods add.
+    // The browser build has no mods folder to read.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn scan(asset_server: &AssetServer) -> Self {
+        let directory = bevy::asset::FileAssetIo::get_base_path().join(DIRECTORY);
+        let mut folders: Vec<String> = match std::fs::read_dir(&directory) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect(),
+            Err(_) => return Mods::default()
+        };
+        folders.sort();
+
+        let mut mods: Vec<Mod> = folders.into_iter()
+            .map(|folder| {
+                let path = directory.join(&folder).join("mod.ron");
+                let manifest = std::fs::read_to_string(&path)
+                    .map_err(|error| Note::Unreadable(error.to_string()))
+                    .and_then(|contents| ron::de::from_str::<Manifest>(&contents)
+                        .map_err(|error| Note::Unreadable(error.to_string())));
+                match manifest {
+                    Ok(manifest) => Mod { folder, manifest: Some(manifest), ..default() },
+                    Err(problem) => Mod { folder, problems: vec![problem], ..default() }
+                }
+            })
+            .collect();
+        for index in 0..mods.len() {
+            let id = match &mods[index].manifest {
+                Some(manifest) => manifest.id.clone(),
+                None => continue
+            };
+            let taken = mods[..index].iter()
+                .find(|other| other.manifest.as_ref().map(|manifest| &manifest.id) == Some(&id));
+            if let Some(other) = taken {
+                let problem = Note::DuplicateId { folder: other.folder.clone(), id };
+                mods[index].manifest = None;
+                mods[index].problems.push(problem);
+            }
+        }
+
+        let order = load_order(&mods);
+        for (index, installed) in mods.iter_mut().enumerate() {
+            if installed.manifest.is_some() && !order.contains(&index) {
+                installed.problems.push(Note::CircularOrder);
+            }
+        }
+        for &index in &order {
+            let installed = &mut mods[index];
+            let manifest = match installed.manifest.clone() {
+                Some(manifest) => manifest,
+                None => continue
+            };
+            let folder = installed.folder.clone();
+            let path = |file: &str| format!("../{}/{}/{}", DIRECTORY, folder, file);
+            installed.enabled = true;
+            installed.customers = manifest.customers.as_deref().map(|file| asset_server.load(&path(file)));
+            installed.ingredients = manifest.ingredients.as_deref().map(|file| asset_server.load(&path(file)));
+            installed.recipes = manifest.recipes.as_deref().map(|file| asset_server.load(&path(file)));
+            installed.strings = manifest.strings.iter()
+                .map(|(language, file)| (language.clone(), asset_server.load(&path(file))))
+                .collect();
+            info!("Loading mod {} from {}", manifest.id, installed.folder);
+        }
+
+        let mut ordered: Vec<Mod> = order.iter().map(|&index| mods[index].clone()).collect();
+        ordered.extend(mods.into_iter().filter(|installed| !installed.enabled));
+        for installed in ordered.iter().filter(|installed| !installed.enabled) {
+            warn!("Not loading the mod in {}: {:?}", installed.folder, installed.problems);
+        }
+        Mods(ordered)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn scan(_asset_server: &AssetServer) -> Self {
+        Mods::default()
+    }
+
+    pub fn enabled(&self) -> impl Iterator<Item = &Mod> {
+        self.0.iter().filter(|installed| installed.enabled)
+    }
+}
+
+// Alphabetical by id, except that a mod comes after the installed mods it
+// names in `after`. Mods caught in a circle are left out.
+fn load_order(mods: &[Mod]) -> Vec<usize> {
+    let ids: Vec<Option<&str>> = mods.iter()
+        .map(|installed| installed.manifest.as_ref().map(|manifest| manifest.id.as_str()))
+        .collect();
+    let mut order: Vec<usize> = Vec::new();
+    loop {
+        let ready = (0..mods.len())
+            .filter(|index| !order.contains(index))
+            .filter_map(|index| mods[index].manifest.as_ref().map(|manifest| (index, manifest)))
+            .filter(|(_, manifest)| manifest.after.iter().all(|after| {
+                let installed = ids.iter().position(|id| *id == Some(after.as_str()));
+                installed.map(|installed| order.contains(&installed)).unwrap_or(true)
+            }))
+            .min_by(|(_, first), (_, second)| first.id.cmp(&second.id));
+        match ready {
+            Some((index, _)) => order.push(index),
+            None => return order
+        }
+    }
+}
+
+// The game's customers, ingredients and recipes with the enabled mods' on
+// top, in load order, so a mod can replace what comes before it by using the
+// same id or the same pair of ingredients. Not ready until every file has
+// either loaded or failed to.
+#[derive(Debug, Clone, Default)]
+pub struct Content {
+    pub ready: bool,
+    pub archetypes: Vec<Archetype>,
+    pub ingredients: Vec<Ingredient>,
+    pub recipes: Vec<Recipe>
+}
+
+fn from_game<T: Clone>(entries: &[T]) -> Vec<(Option<String>, T)> {
+    entries.iter().map(|entry| (None, entry.clone())).collect()
+}
+
+// Adds what a mod brings on top of what came before it, replacing entries
+// with the same key. Each entry is kept with the name of the mod it came from.
+fn merge<T: Clone, K: PartialEq>(entries: &mut Vec<(Option<String>, T)>
+    , added: &[T]
+    , name: &str
+    , key: impl Fn(&T) -> K
+    , mut replaced: impl FnMut(&T, Option<String>))
+{
+    for entry in added {
+        match entries.iter_mut().find(|(_, existing)| key(existing) == key(entry)) {
+            Some((origin, existing)) => {
+                replaced(entry, origin.replace(name.to_string()));
+                *existing = entry.clone();
+            }
+            None => entries.push((Some(name.to_string()), entry.clone()))
+        }
+    }
+}
+
+// A recipe can name its pair either way round, the kitchen looks it up
+// smallest first.
+fn in_order(recipe: &Recipe) -> Recipe {
+    let (first, second) = recipe.ingredients;
+    Recipe {
+        ingredients: (first.min(second), first.max(second)),
+        ..recipe.clone()
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn merge_content(mut customer_events: EventReader<AssetEvent<Customers>>
+    , mut ingredient_events: EventReader<AssetEvent<Ingredients>>
+    , mut recipe_events: EventReader<AssetEvent<Recipes>>
+    , customers_handle: Res<CustomersHandle>
+    , ingredients_handle: Res<IngredientsHandle>
+    , recipes_handle: Res<RecipesHandle>
+    , customers: Res<Assets<Customers>>
+    , ingredients: Res<Assets<Ingredients>>
+    , recipes: Res<Assets<Recipes>>
+    , mut mods: ResMut<Mods>
+    , mut content: ResMut<Content>
+    , asset_server: Res<AssetServer>)
+{
+    let changed = customer_events.iter().count() + ingredient_events.iter().count() + recipe_events.iter().count() > 0;
+    if content.ready && !changed {
+        return;
+    }
+    let Mods(list) = &mut *mods;
+    let pending = [HandleId::from(&customers_handle.0), HandleId::from(&ingredients_handle.0), HandleId::from(&recipes_handle.0)].into_iter()
+        .chain(list.iter().flat_map(|installed| installed.handles().map(|(_, handle)| handle)))
+        .any(|handle| matches!(asset_server.get_load_state(handle), LoadState::NotLoaded | LoadState::Loading));
+    if pending {
+        return;
+    }
+    let base = (customers.get(&customers_handle.0), ingredients.get(&ingredients_handle.0), recipes.get(&recipes_handle.0));
+    let (Customers(base_customers), Ingredients(base_ingredients), Recipes(base_recipes)) = match base {
+        (Some(customers), Some(ingredients), Some(recipes)) => (customers, ingredients, recipes),
+        _ => return
+    };
+
+    let mut archetypes = from_game(base_customers);
+    let mut pantry = from_game(base_ingredients);
+    let mut dishes: Vec<(Option<String>, Recipe)> = base_recipes.iter().map(|recipe| (None, in_order(recipe))).collect();
+    for installed in list.iter_mut().filter(|installed| installed.enabled) {
+        let name = installed.name().to_string();
+        let failed: Vec<Note> = installed.handles()
+            .filter(|(_, handle)| asset_server.get_load_state(*handle) == LoadState::Failed)
+            .map(|(file, _)| Note::NotLoaded(file))
+            .collect();
+        let notes = &mut installed.notes;
+        *notes = failed;
+
+        let added = installed.customers.as_ref().and_then(|handle| customers.get(handle));
+        let added = added.map(|Customers(added)| added.as_slice()).unwrap_or(&[]);
+        merge(&mut archetypes, added, &name, |archetype| archetype.id.clone(), |archetype, origin| {
+            notes.push(Note::ReplacesCustomer { id: archetype.id.clone(), origin });
+        });
+
+        let added = installed.ingredients.as_ref().and_then(|handle| ingredients.get(handle));
+        let added: Vec<Ingredient> = added.iter()
+            .flat_map(|Ingredients(added)| added.iter())
+            .filter(|ingredient| {
+                let known = pantry.iter().any(|(_, existing)| existing.id == ingredient.id);
+                if ingredient.id == 0 {
+                    notes.push(Note::ZeroIngredient);
+                } else if !known && pantry.len() >= MAX_INGREDIENTS {
+                    notes.push(Note::KitchenFull(ingredient.id));
+                } else {
+                    return true;
+                }
+                false
+            })
+            .cloned()
+            .collect();
+        merge(&mut pantry, &added, &name, |ingredient| ingredient.id, |ingredient, origin| {
+            notes.push(Note::ReplacesIngredient { id: ingredient.id, origin });
+        });
+
+        let added = installed.recipes.as_ref().and_then(|handle| recipes.get(handle));
+        let added: Vec<Recipe> = added.iter()
+            .flat_map(|Recipes(added)| added.iter())
+            .filter(|recipe| {
+                let (first, second) = recipe.ingredients;
+                let missing = [first, second].into_iter()
+                    .find(|id| !pantry.iter().any(|(_, ingredient)| ingredient.id == *id));
+                if let Some(missing) = missing {
+                    notes.push(Note::UnknownIngredient { ingredients: recipe.ingredients, missing });
+                }
+                missing.is_none()
+            })
+            .map(in_order)
+            .collect();
+        merge(&mut dishes, &added, &name, |recipe| recipe.ingredients, |recipe, origin| {
+            notes.push(Note::ReplacesRecipe { ingredients: recipe.ingredients, origin });
+        });
+    }
+
+    content.archetypes = archetypes.into_iter().map(|(_, archetype)| archetype).collect();
+    content.ingredients = pantry.into_iter().map(|(_, ingredient)| ingredient).collect();
+    content.recipes = dishes.into_iter().map(|(_, recipe)| recipe).collect();
+    content.ready = true;
+}
+
+pub fn mods_setup(mut commands: Commands
+    , mods: Res<Mods>
+    , locale: Locale
+    , fonts: Res<Fonts>)
+{
+    commands.spawn_bundle(SpriteBundle {
+        sprite: Sprite {
+            color: Color::rgba(0.1, 0.05, 0.1, 0.92),
+            custom_size: Some((DESIGN_WIDTH, DESIGN_HEIGHT).into()),
+            ..default()
+        },
+        ..default()
+    }).insert(ModsScene)
+        .insert(Anchored::center(0., 0., 10.).covering());
+
+    let Mods(list) = &*mods;
+    let mut lines = vec![Localized::new("mods.title", 28.)];
+    if list.is_empty() {
+        lines.push(Localized::new("mods.none", 16.));
+    }
+    for installed in list {
+        let status = if installed.enabled { "mods.enabled" } else { "mods.disabled" };
+        let version = installed.manifest.as_ref()
+            .map(|manifest| manifest.version.as_str())
+            .unwrap_or("");
+        lines.push(Localized::new(status, 20.)
+            .with_arg("name", installed.name())
+            .with_arg("version", version));
+        for (note, color) in installed.problems.iter().map(|problem| (problem, PROBLEM))
+            .chain(installed.notes.iter().map(|note| (note, NOTE)))
+        {
+            let mut line = note.localized();
+            line.color = color;
+            lines.push(line);
+        }
+    }
+    if lines.len() > MAX_LINES {
+        let more = lines.len() - (MAX_LINES - 1);
+        lines.truncate(MAX_LINES - 1);
+        lines.push(Localized::new("mods.more", 14.).with_arg("count", more));
+    }
+
+    let top = DESIGN_HEIGHT/2. - 40.;
+    for (index, line) in lines.into_iter().enumerate() {
+        let y = if index == 0 { top } else { top - 20. - index as f32*ROW_HEIGHT };
+        let (text, effects) = line.text(&locale, &fonts);
+        commands.spawn_bundle(Text2dBundle {
+            text: text.with_alignment(TextAlignment::CENTER),
+            ..default()
+        }).insert(ModsScene)
+            .insert(Anchored::center(0., y, 11.))
+            .insert(line)
+            .insert(effects);
+    }
+}
+
+pub fn mods_input(mut actions: ResMut<Actions>, mut game_state: ResMut<State<GameState>>) {
+    if actions.take(Action::Back) || actions.take(Action::Confirm) {
+        game_state.pop().ok();
+    }
+    actions.clear();
+}
+
+pub fn mods_cleanup(mut commands: Commands, entities: Query<(Entity, &ModsScene)>) {
+    for (entity, _) in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[derive(Component)]
+pub struct ModsScene;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ingredient(id: u32, icon: &str) -> Ingredient {
+        Ingredient { id, icon: icon.to_string(), name: format!("ingredient.{}", icon) }
+    }
+
+    #[test]
+    fn later_entries_replace_earlier_ones() {
+        let mut pantry = from_game(&[ingredient(1, "pumpkin"), ingredient(2, "skull")]);
+        let mut notes = Vec::new();
+        let mut add = |pantry: &mut Vec<_>, added: &[Ingredient], name: &str| {
+            merge(pantry, added, name, |ingredient| ingredient.id, |ingredient, origin| {
+                notes.push(Note::ReplacesIngredient { id: ingredient.id, origin });
+            });
+        };
+        add(&mut pantry, &[ingredient(2, "bone"), ingredient(7, "eyeball")], "Spooky");
+        add(&mut pantry, &[ingredient(7, "marble")], "Marbles");
+        assert_eq!(pantry, vec![
+            (None, ingredient(1, "pumpkin")),
+            (Some("Spooky".to_string()), ingredient(2, "bone")),
+            (Some("Marbles".to_string()), ingredient(7, "marble"))
+        ]);
+        assert_eq!(notes, vec![
+            Note::ReplacesIngredient { id: 2, origin: None },
+            Note::ReplacesIngredient { id: 7, origin: Some("Spooky".to_string()) }
+        ]);
+    }
+
+    #[test]
+    fn recipes_match_either_way_round() {
+        let recipe = |first, second, description: &str| Recipe {
+            ingredients: (first, second),
+            image: String::new(),
+            description: description.to_string(),
+            sweet: 0,
+            savory: 0,
+            spooky: 0
+        };
+        let mut dishes: Vec<_> = [recipe(7, 1, "recipe.eyeball_pie")].iter().map(|recipe| (None, in_order(recipe))).collect();
+        let added: Vec<Recipe> = [recipe(1, 7, "recipe.eyeball_tart")].iter().map(in_order).collect();
+        let mut replaced = Vec::new();
+        merge(&mut dishes, &added, "Spooky", |recipe| recipe.ingredients, |recipe, origin| replaced.push((recipe.ingredients, origin)));
+        assert_eq!(dishes, vec![(Some("Spooky".to_string()), recipe(1, 7, "recipe.eyeball_tart"))]);
+        assert_eq!(replaced, vec![((1, 7), None)]);
+    }
+
+    #[test]
+    fn notes_name_the_game_or_the_mod() {
+        assert_eq!(Note::ReplacesRecipe { ingredients: (1, 7), origin: None }.localized(),
+            Localized::new("mods.replaces_game_recipe", 14.).with_arg("first", 1).with_arg("second", 7));
+        assert_eq!(Note::ReplacesCustomer { id: "lady".to_string(), origin: Some("Spooky".to_string()) }.localized(),
+            Localized::new("mods.replaces_customer", 14.).with_arg("origin", "Spooky").with_arg("id", "lady"));
+    }
+}
@@ -0,0 +1,74 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+use crate::actions::{Action, Actions};
+use crate::common::*;
+use crate::layout::{Anchored, DESIGN_HEIGHT, DESIGN_WIDTH};
+use crate::locale::Locale;
+use crate::menu::{spawn_menu, Menu};
+use crate::transition::Transition;
+
+const DEPTH: f32 = 10.;
+const ENTRIES: [&str; 4] = ["pause.resume", "pause.settings", "pause.title_screen", "pause.quit"];
+
+pub fn pause_setup(mut commands: Commands
+    , locale: Locale
+    , fonts: Res<Fonts>)
+{
+    commands.spawn_bundle(SpriteBundle {
+        sprite: Sprite {
+            color: Color::rgba(0., 0., 0., 0.75),
+            custom_size: Some((DESIGN_WIDTH, DESIGN_HEIGHT).into()),
+            ..default()
+        },
+        ..default()
+    }).insert(PauseScene)
+        .insert(Anchored::center(0., 0., DEPTH).covering());
+
+    let entries = ENTRIES.map(|key| (key, true));
+    spawn_menu(&mut commands, PauseScene, DEPTH + 1., "pause.title", &entries, &locale, &fonts);
+}
+
+pub fn pause_input(mut actions: ResMut<Actions>
+    , mut menu: Query<&mut Menu, With<PauseScene>>
+    , mut game_state: ResMut<State<GameState>>
+    , mut transition: ResMut<Transition>
+    , mut exit: EventWriter<AppExit>)
+{
+    let picked = menu.get_single_mut().ok().and_then(|mut menu| menu.navigate(&mut actions));
+    let resume = actions.take(Action::Pause) || actions.take(Action::Back);
+    actions.clear();
+    match picked.map(|index| ENTRIES[index]) {
+        Some("pause.resume") => {
+            game_state.pop().ok();
+        }
+        Some("pause.settings") => {
+            game_state.push(GameState::Settings).ok();
+        }
+        Some("pause.title_screen") => {
+            transition.start(game_state.current(), GameState::Title);
+        }
+        Some("pause.quit") => exit.send(AppExit),
+        _ if resume => {
+            game_state.pop().ok();
+        }
+        _ => ()
+    }
+}
+
+pub fn pause_cleanup(mut commands: Commands, entities: Query<(Entity, &PauseScene)>) {
+    for (entity, _) in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// Escape freezes whichever scene is playing under the pause menu.
+pub fn open_pause(mut actions: ResMut<Actions>, mut game_state: ResMut<State<GameState>>) {
+    let playing = matches!(game_state.current(), GameState::Dialogue | GameState::Cooking);
+    if playing && actions.take(Action::Pause) {
+        game_state.push(GameState::Paused).ok();
+    }
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct PauseScene;
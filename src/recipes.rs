@@ -0,0 +1,115 @@
+use std::marker::PhantomData;
+
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use serde::Deserialize;
+
+use crate::mods::Content;
+
+// Something to cook with. `name` is a string table key and `id` is what
+// recipes call it by.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Ingredient {
+    pub id: u32,
+    pub icon: String,
+    pub name: String
+}
+
+// Every ingredient in the kitchen, loaded from `game.ingredients.ron`.
+#[derive(Debug, Clone, Default, TypeUuid)]
+#[uuid = "5a7d2e91-c83b-4f16-9d4a-0b6e1f3c8a25"]
+pub struct Ingredients(pub Vec<Ingredient>);
+
+#[derive(Default)]
+pub struct IngredientsLoader;
+
+impl AssetLoader for IngredientsLoader {
+    fn load<'a>(&'a self
+        , bytes: &'a [u8]
+        , load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), anyhow::Error>>
+    {
+        Box::pin(async move {
+            let ingredients: Vec<Ingredient> = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(Ingredients(ingredients)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ingredients.ron"]
+    }
+}
+
+pub struct IngredientsHandle(pub Handle<Ingredients>);
+
+impl IngredientsHandle {
+    pub fn load(asset_server: &AssetServer) -> Self {
+        IngredientsHandle(asset_server.load("game.ingredients.ron"))
+    }
+}
+
+// What a pair of ingredients makes. `description` is a string table key.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Recipe {
+    pub ingredients: (u32, u32),
+    pub image: String,
+    pub description: String,
+    pub sweet: u32,
+    pub savory: u32,
+    pub spooky: u32
+}
+
+// Every recipe, loaded from `game.recipes.ron`.
+#[derive(Debug, Clone, Default, TypeUuid)]
+#[uuid = "3f9e6b12-7c4d-4a85-b1e0-52d8c6a9f473"]
+pub struct Recipes(pub Vec<Recipe>);
+
+#[derive(Default)]
+pub struct RecipesLoader;
+
+impl AssetLoader for RecipesLoader {
+    fn load<'a>(&'a self
+        , bytes: &'a [u8]
+        , load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), anyhow::Error>>
+    {
+        Box::pin(async move {
+            let recipes: Vec<Recipe> = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(Recipes(recipes)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["recipes.ron"]
+    }
+}
+
+pub struct RecipesHandle(pub Handle<Recipes>);
+
+impl RecipesHandle {
+    pub fn load(asset_server: &AssetServer) -> Self {
+        RecipesHandle(asset_server.load("game.recipes.ron"))
+    }
+}
+
+#[derive(SystemParam)]
+pub struct RecipeList<'w, 's> {
+    content: Res<'w, Content>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>
+}
+
+impl<'w, 's> RecipeList<'w, 's> {
+    // Empty until the recipes and ingredients, and those of any mods, have loaded.
+    pub fn recipes(&self) -> &[Recipe] {
+        &self.content.recipes
+    }
+
+    // In the order the kitchen lays them out.
+    pub fn ingredients(&self) -> &[Ingredient] {
+        &self.content.ingredients
+    }
+}
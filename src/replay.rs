@@ -0,0 +1,266 @@
+use std::time::Duration;
+
+use bevy::app::AppExit;
+use bevy::asset::{AssetPlugin, HandleId, LoadState};
+use bevy::hierarchy::HierarchyPlugin;
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use bevy::time::TimePlugin;
+use bevy::utils::Instant;
+use serde::{Deserialize, Serialize};
+
+use crate::actions::{Action, ActionSystem, Actions};
+use crate::calendar::StoryEventsHandle;
+use crate::common::*;
+use crate::customers::CustomersHandle;
+use crate::dialogue::{DialogueAutoplay, DialogueMode, DialogueState, ReadLines};
+use crate::locale::Localization;
+use crate::mods::Content;
+use crate::recipes::{IngredientsHandle, RecipesHandle};
+use crate::save::{Progress, SaveGame};
+use crate::storage;
+use crate::transition::{Transition, TransitionsHandle};
+
+// A run as it was played, from the moment its first scene could be played:
+// where it started, the language, how long every frame took, the actions of
+// each frame and a checksum of the dialogue state after the last one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    pub seed: u64,
+    pub start: SaveGame,
+    pub read_lines: ReadLines,
+    pub mode: DialogueMode,
+    pub language: String,
+    // Nanoseconds, so replayed timers tick exactly as they did.
+    pub frames: Vec<u64>,
+    pub actions: Vec<(u32, Action)>,
+    pub checksum: u64
+}
+
+// The run being recorded with `--record <file>`. The file is written once,
+// when the run goes back to the title or the game closes, ready to attach to
+// a bug report. Only the first run of the session is recorded.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    path: String,
+    recording: Option<Recording>,
+    finished: bool
+}
+
+impl Recorder {
+    pub fn new(path: &str) -> Self {
+        Recorder {
+            path: path.to_string(),
+            recording: None,
+            finished: false
+        }
+    }
+
+    fn write(&mut self) {
+        self.finished = true;
+        let recording = match self.recording.take() {
+            Some(recording) => recording,
+            None => return
+        };
+        let result = ron::ser::to_string_pretty(&recording, default())
+            .map_err(|error| error.to_string())
+            .and_then(|contents| std::fs::write(&self.path, contents).map_err(|error| error.to_string()));
+        match result {
+            Ok(()) => info!("Wrote {} frames of the run to {}", recording.frames.len(), self.path),
+            Err(error) => error!("Could not write the recording to {}: {}", self.path, error)
+        }
+    }
+}
+
+// FNV-1a of the state as RON, which lists everything in a fixed order.
+pub fn checksum(state: &DialogueState) -> u64 {
+    let contents = ron::ser::to_string(state).unwrap_or_default();
+    contents.bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100_0000_01b3))
+}
+
+// Starts recording once a run's first scene is showing and stops back on the
+// title. Runs right after the actions are collected, before anything takes them.
+#[allow(clippy::too_many_arguments)]
+pub fn record_actions(mut recorder: ResMut<Recorder>
+    , mut last_state: Local<Option<GameState>>
+    , actions: Res<Actions>
+    , progress: Progress
+    , read_lines: Res<ReadLines>
+    , autoplay: Res<DialogueAutoplay>
+    , localization: Res<Localization>
+    , game_state: Res<State<GameState>>
+    , transition: Res<Transition>
+    , time: Res<Time>)
+{
+    if recorder.finished {
+        return;
+    }
+    if recorder.recording.is_none() && *game_state.current() == GameState::Dialogue && !transition.is_running() {
+        recorder.recording = Some(Recording {
+            seed: progress.rng.seed,
+            start: progress.save(),
+            read_lines: read_lines.clone(),
+            mode: autoplay.mode,
+            language: localization.language.clone(),
+            frames: Vec::new(),
+            actions: Vec::new(),
+            checksum: 0
+        });
+    }
+    let current = match &mut recorder.recording {
+        Some(current) => current,
+        None => return
+    };
+
+    let state = game_state.current();
+    if last_state.as_ref() != Some(state) {
+        *last_state = Some(state.clone());
+        current.checksum = checksum(&progress.dialogue);
+    }
+    if *state == GameState::Title {
+        recorder.write();
+        return;
+    }
+    let frame = current.frames.len() as u32;
+    current.frames.push(time.delta().as_nanos() as u64);
+    let mut pressed: Vec<Action> = actions.iter().collect();
+    pressed.sort();
+    current.actions.extend(pressed.into_iter().map(|action| (frame, action)));
+}
+
+// Writes the recording if the game closes mid-run.
+pub fn save_recording(mut exit: EventReader<AppExit>
+    , mut recorder: ResMut<Recorder>
+    , dialogue: Res<DialogueState>)
+{
+    if exit.iter().count() == 0 || recorder.finished {
+        return;
+    }
+    if let Some(current) = &mut recorder.recording {
+        current.checksum = checksum(&dialogue);
+    }
+    recorder.write();
+}
+
+// Where a replay is up to. Nothing plays until the data it depends on has loaded.
+struct Replay {
+    recording: Recording,
+    started: bool,
+    frame: usize,
+    next_action: usize,
+    clock: Instant
+}
+
+// `--replay <file>`: plays a recording back without a window or sound and
+// checks the run ends up where it did when it was recorded. Nothing is saved
+// while it plays.
+pub fn run(path: &str) -> ! {
+    let recording: Result<Recording, String> = std::fs::read_to_string(path)
+        .map_err(|error| error.to_string())
+        .and_then(|contents| ron::de::from_str(&contents).map_err(|error| error.to_string()));
+    let recording = match recording {
+        Ok(recording) => recording,
+        Err(error) => {
+            println!("Could not read {}: {}", path, error);
+            std::process::exit(1);
+        }
+    };
+    println!("Replaying {} frames of a run with seed {}", recording.frames.len(), recording.seed);
+    storage::set_read_only();
+
+    let mut time = Time::default();
+    let clock = Instant::now();
+    time.update_with_instant(clock);
+    let mut app = App::new();
+    app.add_plugins_with(MinimalPlugins, |group| group.disable::<TimePlugin>())
+        .add_plugin(AssetPlugin)
+        .add_plugin(InputPlugin)
+        .add_plugin(HierarchyPlugin)
+        .insert_resource(time)
+        .insert_resource(Windows::default());
+    crate::add_game(&mut app);
+    app.insert_resource(Replay {
+            recording,
+            started: false,
+            frame: 0,
+            next_action: 0,
+            clock
+        })
+        .add_system_to_stage(CoreStage::PreUpdate, replay_actions.label(ActionSystem))
+        .run();
+    std::process::exit(0);
+}
+
+// Stands in for both the clock and the player.
+#[allow(clippy::too_many_arguments)]
+fn replay_actions(mut replay: ResMut<Replay>
+    , mut actions: ResMut<Actions>
+    , mut progress: Progress
+    , mut read_lines: ResMut<ReadLines>
+    , mut autoplay: ResMut<DialogueAutoplay>
+    , mut localization: ResMut<Localization>
+    , mut game_state: ResMut<State<GameState>>
+    , mut time: ResMut<Time>
+    , content: Res<Content>
+    , customers: Res<CustomersHandle>
+    , events: Res<StoryEventsHandle>
+    , ingredients: Res<IngredientsHandle>
+    , recipes: Res<RecipesHandle>
+    , transitions: Res<TransitionsHandle>
+    , asset_server: Res<AssetServer>
+    , mut exit: EventWriter<AppExit>)
+{
+    actions.clear();
+    if !replay.started {
+        let handles = localization.tables()
+            .map(HandleId::from)
+            .chain([HandleId::from(&customers.0), HandleId::from(&events.0), HandleId::from(&ingredients.0)
+                , HandleId::from(&recipes.0), HandleId::from(&transitions.0)]);
+        match asset_server.get_group_load_state(handles) {
+            LoadState::Loaded => (),
+            LoadState::Failed => {
+                println!("Replay failed: the game data could not be loaded");
+                std::process::exit(1);
+            }
+            _ => return
+        }
+        // Mods have to be in as well.
+        if !content.ready {
+            return;
+        }
+        progress.restore(replay.recording.start.clone());
+        *read_lines = replay.recording.read_lines.clone();
+        autoplay.mode = replay.recording.mode;
+        localization.language = replay.recording.language.clone();
+        game_state.set(GameState::Dialogue).ok();
+        replay.started = true;
+    }
+
+    let Replay { recording, frame, next_action, clock, .. } = &mut *replay;
+    let nanoseconds = match recording.frames.get(*frame) {
+        Some(nanoseconds) => *nanoseconds,
+        None => {
+            let reached = checksum(&progress.dialogue);
+            if reached == recording.checksum {
+                println!("Replay matches: dialogue state checksum {:016x}", reached);
+            } else {
+                println!("Replay diverged: dialogue state checksum {:016x}, recorded {:016x}", reached, recording.checksum);
+                println!("{:#?}", *progress.dialogue);
+                std::process::exit(1);
+            }
+            exit.send(AppExit);
+            return;
+        }
+    };
+    *clock += Duration::from_nanos(nanoseconds);
+    time.update_with_instant(*clock);
+    while let Some((stamp, action)) = recording.actions.get(*next_action) {
+        if *stamp as usize != *frame {
+            break;
+        }
+        actions.press(*action);
+        *next_action += 1;
+    }
+    *frame += 1;
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::customers::Archetype;
+
+const LIMIT: i32 = 100;
+const CUSTOMER_CHANGE: i32 = 10;
+const FACTION_CHANGE: i32 = 4;
+// The standing at which a customer greets the witch as a friend, or warily.
+const FOND: i32 = 20;
+const WARY: i32 = -20;
+
+// How a customer feels about the shop when they walk in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Standing {
+    Wary,
+    #[default]
+    Neutral,
+    Fond
+}
+
+// What each customer and each faction thinks of the shop this run, from -100 to 100.
+// A customer's own opinion counts fully and their faction's gossip half as much.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reputation {
+    pub customers: HashMap<String, i32>,
+    pub factions: HashMap<String, i32>
+}
+
+impl Reputation {
+    pub fn customer(&self, id: &str) -> i32 {
+        self.customers.get(id).copied().unwrap_or(0)
+    }
+
+    pub fn faction(&self, id: &str) -> i32 {
+        self.factions.get(id).copied().unwrap_or(0)
+    }
+
+    pub fn of(&self, archetype: &Archetype) -> i32 {
+        let faction = archetype.faction.as_deref().map(|faction| self.faction(faction)).unwrap_or(0);
+        (self.customer(&archetype.id) + faction/2).clamp(-LIMIT, LIMIT)
+    }
+
+    pub fn standing(&self, archetype: &Archetype) -> Standing {
+        match self.of(archetype) {
+            reputation if reputation >= FOND => Standing::Fond,
+            reputation if reputation <= WARY => Standing::Wary,
+            _ => Standing::Neutral
+        }
+    }
+
+    // Scales how often a customer comes by: twice as often at best, hardly ever at worst.
+    pub fn appeal(&self, archetype: &Archetype) -> f32 {
+        (1. + self.of(archetype) as f32/LIMIT as f32).max(0.1)
+    }
+
+    pub fn serve(&mut self, archetype: &Archetype, delighted: bool) {
+        let sign = if delighted { 1 } else { -1 };
+        let customer = self.customers.entry(archetype.id.clone()).or_insert(0);
+        *customer = (*customer + sign*CUSTOMER_CHANGE).clamp(-LIMIT, LIMIT);
+        if let Some(faction) = &archetype.faction {
+            let faction = self.factions.entry(faction.clone()).or_insert(0);
+            *faction = (*faction + sign*FACTION_CHANGE).clamp(-LIMIT, LIMIT);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+// A small SplitMix64 generator. Its whole state is one number, so it can be
+// saved and restored to replay exactly the same sequence.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rng {
+    state: u64
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    // A generator for one named stream of `seed`, unrelated to the other streams.
+    pub fn stream(seed: u64, name: &str) -> Self {
+        let salt = name.bytes()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100_0000_01b3));
+        Rng::new(Rng::new(seed ^ salt).next_u64())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1).
+    pub fn unit(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32/(1u64 << 24) as f32
+    }
+
+    // Uniform in [0, n), or 0 when there is nothing to choose from.
+    pub fn below(&mut self, n: usize) -> usize {
+        if n == 0 { 0 } else { (self.next_u64() % n as u64) as usize }
+    }
+
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.unit() < probability
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        items.get(self.below(items.len()))
+    }
+
+    // An index chosen in proportion to its weight, ignoring anything not positive.
+    pub fn weighted(&mut self, weights: &[f32]) -> Option<usize> {
+        let total: f32 = weights.iter().filter(|weight| **weight > 0.).sum();
+        if total <= 0. {
+            return None;
+        }
+        let mut target = self.unit()*total;
+        for (index, weight) in weights.iter().enumerate().filter(|(_, weight)| **weight > 0.) {
+            if target < *weight {
+                return Some(index);
+            }
+            target -= weight;
+        }
+        weights.iter().rposition(|weight| *weight > 0.)
+    }
+}
+
+// Every random decision of a run, split into a stream per subsystem so that
+// drawing more numbers in one leaves the sequences of the others untouched.
+// `customers` picks who comes in and how their visit goes, `cooking` how they
+// react to the dish. The calendar has nothing random yet; it gets its own
+// stream once it does.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameRng {
+    pub seed: u64,
+    pub customers: Rng,
+    pub cooking: Rng
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng::new(0)
+    }
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng {
+            seed,
+            customers: Rng::stream(seed, "customers"),
+            cooking: Rng::stream(seed, "cooking")
+        }
+    }
+}
+
+// The seed every new game starts from, if one was asked for with `--seed <number>`
+// or a `seed` file in the save directory. Otherwise each game gets a fresh one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Seed(pub Option<u64>);
+
+impl Seed {
+    pub fn load() -> Self {
+        let flag = std::env::args().skip_while(|arg| arg != "--seed").nth(1);
+        match flag.map(|seed| seed.parse()) {
+            Some(Ok(seed)) => Seed(Some(seed)),
+            Some(Err(error)) => {
+                warn!("Ignoring --seed: {}", error);
+                Seed(storage::load("seed"))
+            }
+            None => Seed(storage::load("seed"))
+        }
+    }
+
+    pub fn pick(&self, time: &Time) -> u64 {
+        // However long the player lingered on the title makes a good enough seed.
+        self.0.unwrap_or_else(|| time.seconds_since_startup().to_bits())
+    }
+}
@@ -0,0 +1,107 @@
+use std::marker::PhantomData;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::calendar::{Calendar, StoryEvent};
+use crate::campaign::Campaign;
+use crate::customers::{Archetype, Visits};
+use crate::dialogue::DialogueState;
+use crate::reputation::Reputation;
+use crate::rng::GameRng;
+use crate::storage;
+
+// The progress of the current run, written whenever a dialogue scene starts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub dialogue: DialogueState,
+    pub visits: Visits,
+    #[serde(default)]
+    pub campaign: Campaign,
+    #[serde(default)]
+    pub calendar: Calendar,
+    #[serde(default)]
+    pub reputation: Reputation,
+    #[serde(default)]
+    pub rng: GameRng
+}
+
+impl SaveGame {
+    pub fn load() -> Option<SaveGame> {
+        storage::load("save")
+    }
+
+    pub fn store(&self) {
+        storage::store("save", &Some(self));
+    }
+
+    pub fn clear() {
+        storage::store::<Option<SaveGame>>("save", &None);
+    }
+}
+
+// Every resource that belongs to the current run, so a system can start,
+// save or restore one without naming them all.
+#[derive(SystemParam)]
+pub struct Progress<'w, 's> {
+    pub dialogue: ResMut<'w, DialogueState>,
+    pub visits: ResMut<'w, Visits>,
+    pub campaign: ResMut<'w, Campaign>,
+    pub calendar: ResMut<'w, Calendar>,
+    pub reputation: ResMut<'w, Reputation>,
+    pub rng: ResMut<'w, GameRng>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>
+}
+
+impl<'w, 's> Progress<'w, 's> {
+    // Opens the shop on the first day of a new run.
+    pub fn start(&mut self
+        , seed: u64
+        , reputation: Reputation
+        , cycle: u32
+        , archetypes: &[Archetype]
+        , events: &[StoryEvent])
+    {
+        *self.dialogue = DialogueState::default();
+        *self.visits = Visits::default();
+        *self.campaign = Campaign {
+            cycle,
+            ..default()
+        };
+        *self.calendar = Calendar::default();
+        *self.reputation = reputation;
+        *self.rng = GameRng::new(seed);
+        self.calendar.start_day(events, archetypes, &self.reputation, &self.dialogue.arcs);
+        self.next_customer(archetypes);
+    }
+
+    pub fn next_customer(&mut self, archetypes: &[Archetype]) {
+        self.dialogue.next_customer(archetypes, &mut self.visits, &self.reputation, &mut self.calendar, &mut self.rng.customers);
+    }
+
+    pub fn save(&self) -> SaveGame {
+        SaveGame {
+            dialogue: self.dialogue.clone(),
+            visits: self.visits.clone(),
+            campaign: self.campaign.clone(),
+            calendar: self.calendar.clone(),
+            reputation: self.reputation.clone(),
+            rng: self.rng.clone()
+        }
+    }
+
+    pub fn restore(&mut self, save: SaveGame) {
+        *self.dialogue = save.dialogue;
+        *self.visits = save.visits;
+        *self.campaign = save.campaign;
+        *self.calendar = save.calendar;
+        *self.reputation = save.reputation;
+        *self.rng = save.rng;
+    }
+}
+
+pub fn autosave(progress: Progress) {
+    progress.save().store();
+}
@@ -0,0 +1,168 @@
+use bevy::prelude::*;
+use bevy::window::WindowMode;
+
+use crate::actions::{Action, Actions};
+use crate::audio::Volume;
+use crate::common::*;
+use crate::layout::{self, Anchored, DESIGN_HEIGHT, DESIGN_WIDTH};
+use crate::locale::{Locale, Localization, Localized};
+use crate::menu::{spawn_menu, Menu, MenuEntry};
+
+const DEPTH: f32 = 20.;
+// How much one press of Left or Right changes a volume.
+const VOLUME_STEP: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Setting {
+    MasterVolume,
+    MusicVolume,
+    EffectsVolume,
+    Language,
+    Fullscreen,
+    Controls,
+    Back
+}
+
+const SETTINGS: [Setting; 7] = [
+    Setting::MasterVolume,
+    Setting::MusicVolume,
+    Setting::EffectsVolume,
+    Setting::Language,
+    Setting::Fullscreen,
+    Setting::Controls,
+    Setting::Back
+];
+
+impl Setting {
+    fn key(&self) -> &'static str {
+        match self {
+            Setting::MasterVolume => "settings.master",
+            Setting::MusicVolume => "settings.music",
+            Setting::EffectsVolume => "settings.effects",
+            Setting::Language => "settings.language",
+            Setting::Fullscreen => "settings.fullscreen",
+            Setting::Controls => "settings.controls",
+            Setting::Back => "settings.back"
+        }
+    }
+}
+
+pub fn settings_setup(mut commands: Commands
+    , locale: Locale
+    , fonts: Res<Fonts>)
+{
+    commands.spawn_bundle(SpriteBundle {
+        sprite: Sprite {
+            color: Color::rgba(0., 0., 0., 0.9),
+            custom_size: Some((DESIGN_WIDTH, DESIGN_HEIGHT).into()),
+            ..default()
+        },
+        ..default()
+    }).insert(SettingsScene)
+        .insert(Anchored::center(0., 0., DEPTH).covering());
+
+    let entries = SETTINGS.map(|setting| (setting.key(), true));
+    spawn_menu(&mut commands, SettingsScene, DEPTH + 1., "settings.title", &entries, &locale, &fonts);
+
+    let hint = Localized::new("settings.hint", 16.);
+    let (text, effects) = hint.text(&locale, &fonts);
+    commands.spawn_bundle(Text2dBundle {
+        text: text.with_alignment(TextAlignment::CENTER),
+        ..default()
+    }).insert(SettingsScene)
+        .insert(Anchored::center(0., -DESIGN_HEIGHT/2. + 30., DEPTH + 1.))
+        .insert(hint)
+        .insert(effects);
+}
+
+// Fills the current value of every setting into its entry.
+pub fn settings_values(mut entries: Query<(&MenuEntry, &mut Localized), With<SettingsScene>>
+    , volume: Res<Volume>
+    , windows: Res<Windows>
+    , locale: Locale)
+{
+    let fullscreen = windows.get_primary().map(|window| window.mode() != WindowMode::Windowed).unwrap_or(false);
+    for (entry, mut localized) in entries.iter_mut() {
+        let value = match SETTINGS[entry.index] {
+            Setting::MasterVolume => percent(volume.master),
+            Setting::MusicVolume => percent(volume.music),
+            Setting::EffectsVolume => percent(volume.effects),
+            Setting::Language => locale.get(&format!("language.{}", locale.language())).to_string(),
+            Setting::Fullscreen => locale.get(if fullscreen { "settings.on" } else { "settings.off" }).to_string(),
+            Setting::Controls | Setting::Back => continue
+        };
+        let args = vec![("value".to_string(), value)];
+        if localized.args != args {
+            localized.args = args;
+        }
+    }
+}
+
+pub fn settings_input(mut actions: ResMut<Actions>
+    , mut menu: Query<&mut Menu, With<SettingsScene>>
+    , mut volume: ResMut<Volume>
+    , mut localization: ResMut<Localization>
+    , mut windows: ResMut<Windows>
+    , mut game_state: ResMut<State<GameState>>)
+{
+    let mut menu = match menu.get_single_mut() {
+        Ok(menu) => menu,
+        Err(_) => return
+    };
+    let picked = menu.navigate(&mut actions).map(|index| SETTINGS[index]);
+    let selected = SETTINGS[menu.selected];
+    let change = if actions.take(Action::Left) {
+        -VOLUME_STEP
+    } else if actions.take(Action::Right) {
+        VOLUME_STEP
+    } else {
+        0.
+    };
+    let close = actions.take(Action::Back) || actions.take(Action::Pause);
+    actions.clear();
+
+    if change != 0. {
+        let value = match selected {
+            Setting::MasterVolume => Some(&mut volume.master),
+            Setting::MusicVolume => Some(&mut volume.music),
+            Setting::EffectsVolume => Some(&mut volume.effects),
+            _ => None
+        };
+        if let Some(value) = value {
+            *value = ((*value + change)*10.).round().clamp(0., 10.)/10.;
+            volume.save();
+        }
+    }
+    if change != 0. || picked.is_some() {
+        match selected {
+            Setting::Language => localization.next_language(),
+            Setting::Fullscreen => layout::switch_fullscreen(&mut windows),
+            _ => ()
+        }
+    }
+    match picked {
+        Some(Setting::Controls) => {
+            game_state.push(GameState::Controls).ok();
+        }
+        Some(Setting::Back) => {
+            game_state.pop().ok();
+        }
+        _ if close => {
+            game_state.pop().ok();
+        }
+        _ => ()
+    }
+}
+
+pub fn settings_cleanup(mut commands: Commands, entities: Query<(Entity, &SettingsScene)>) {
+    for (entity, _) in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn percent(value: f32) -> String {
+    format!("{}", (value*100.).round())
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct SettingsScene;
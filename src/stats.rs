@@ -0,0 +1,272 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::actions::{Action, Actions};
+use crate::common::*;
+use crate::cooking::CookingSelection;
+use crate::customers::Roster;
+use crate::events::{CustomerReacted, DayEnded, DishCooked, DishServed};
+use crate::layout::{Anchored, DESIGN_HEIGHT, DESIGN_WIDTH};
+use crate::locale::{Locale, Localized};
+use crate::recipes::{Recipe, RecipeList};
+use crate::storage;
+
+const ROW_HEIGHT: f32 = 26.;
+const COLUMN: f32 = 260.;
+// How many of the most cooked dishes the stats screen lists.
+const TOP_DISHES: usize = 8;
+// And of the customers served most.
+const TOP_CUSTOMERS: usize = 8;
+const MENUS: [GameState; 7] = [GameState::Title, GameState::Paused, GameState::Settings, GameState::Controls
+    , GameState::Achievements, GameState::Stats, GameState::Mods];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Satisfaction {
+    pub delighted: u32,
+    pub disappointed: u32
+}
+
+impl Satisfaction {
+    pub fn served(&self) -> u32 {
+        self.delighted + self.disappointed
+    }
+}
+
+// What players do over every run: dishes cooked and served by recipe, how
+// each customer took their dishes, how many dishes were failures, days
+// played and seconds spent in each game state.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub cooked: BTreeMap<String, u32>,
+    pub served: BTreeMap<String, u32>,
+    pub customers: BTreeMap<String, Satisfaction>,
+    pub failures: u32,
+    pub days: u32,
+    pub seconds: BTreeMap<String, f32>
+}
+
+impl Stats {
+    pub fn load() -> Self {
+        storage::load("stats")
+    }
+
+    pub fn store(&self) {
+        storage::store("stats", self);
+    }
+
+    fn seconds_in(&self, states: &[GameState]) -> f32 {
+        states.iter()
+            .filter_map(|state| self.seconds.get(&format!("{:?}", state)))
+            .sum()
+    }
+
+    // One `section,name,value` row per number.
+    pub fn csv(&self) -> String {
+        let mut rows = vec!["section,name,value".to_string()];
+        for (recipe, count) in &self.cooked {
+            rows.push(format!("cooked,{},{}", recipe, count));
+        }
+        for (recipe, count) in &self.served {
+            rows.push(format!("served,{},{}", recipe, count));
+        }
+        for (customer, satisfaction) in &self.customers {
+            rows.push(format!("delighted,{},{}", customer, satisfaction.delighted));
+            rows.push(format!("disappointed,{},{}", customer, satisfaction.disappointed));
+        }
+        rows.push(format!("failures,,{}", self.failures));
+        rows.push(format!("days,,{}", self.days));
+        for (state, seconds) in &self.seconds {
+            rows.push(format!("seconds,{},{:.1}", state, seconds));
+        }
+        rows.join("\n") + "\n"
+    }
+}
+
+// Recipes are named after their description, so failures read `failure.<pair>`
+// and an empty pot `none`.
+fn recipe(selection: &CookingSelection, recipes: &[Recipe]) -> String {
+    let (_, description, ..) = selection.combination_data(recipes);
+    description.trim_start_matches("recipe.").to_string()
+}
+
+pub fn count_stats(mut cooked: EventReader<DishCooked>
+    , mut served: EventReader<DishServed>
+    , mut reacted: EventReader<CustomerReacted>
+    , mut day_ended: EventReader<DayEnded>
+    , mut stats: ResMut<Stats>
+    , recipes: RecipeList)
+{
+    let recipes = recipes.recipes();
+    let mut counted = false;
+    for dish in cooked.iter() {
+        *stats.cooked.entry(recipe(&dish.selection, recipes)).or_default() += 1;
+        if !dish.selection.is_dish(recipes) {
+            stats.failures += 1;
+        }
+        counted = true;
+    }
+    for dish in served.iter() {
+        *stats.served.entry(recipe(&dish.selection, recipes)).or_default() += 1;
+        counted = true;
+    }
+    for reaction in reacted.iter() {
+        let satisfaction = stats.customers.entry(reaction.customer.clone()).or_default();
+        if reaction.delighted {
+            satisfaction.delighted += 1;
+        } else {
+            satisfaction.disappointed += 1;
+        }
+        counted = true;
+    }
+    for _ in day_ended.iter() {
+        stats.days += 1;
+        counted = true;
+    }
+    if counted {
+        stats.store();
+    }
+}
+
+// Written out whenever the state changes, so the time isn't lost on quitting.
+pub fn time_states(game_state: Res<State<GameState>>
+    , mut last_state: Local<Option<GameState>>
+    , mut stats: ResMut<Stats>
+    , time: Res<Time>)
+{
+    let state = game_state.current();
+    *stats.seconds.entry(format!("{:?}", state)).or_default() += time.delta_seconds();
+    if last_state.as_ref() != Some(state) {
+        *last_state = Some(state.clone());
+        stats.store();
+    }
+}
+
+// `--export-stats <file>`: writes the stats as JSON, or as CSV if the file
+// name ends in `.csv`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn export(path: &str) -> bool {
+    let stats = Stats::load();
+    let contents = if path.ends_with(".csv") {
+        Ok(stats.csv())
+    } else {
+        serde_json::to_string_pretty(&stats).map_err(|error| error.to_string())
+    };
+    let result = contents.and_then(|contents| std::fs::write(path, contents).map_err(|error| error.to_string()));
+    match result {
+        Ok(()) => {
+            println!("Wrote stats to {}", path);
+            true
+        }
+        Err(error) => {
+            println!("Could not export stats to {}: {}", path, error);
+            false
+        }
+    }
+}
+
+pub fn stats_setup(mut commands: Commands
+    , stats: Res<Stats>
+    , roster: Roster
+    , locale: Locale
+    , fonts: Res<Fonts>)
+{
+    commands.spawn_bundle(SpriteBundle {
+        sprite: Sprite {
+            color: Color::rgba(0.1, 0.05, 0.1, 0.92),
+            custom_size: Some((DESIGN_WIDTH, DESIGN_HEIGHT).into()),
+            ..default()
+        },
+        ..default()
+    }).insert(StatsScene)
+        .insert(Anchored::center(0., 0., 10.).covering());
+
+    let top = DESIGN_HEIGHT/2. - 40.;
+    let row = |index: usize| top - 84. - (index + 1) as f32*ROW_HEIGHT;
+    let cooked: u32 = stats.cooked.values().sum();
+    let mut lines = vec![
+        (Localized::new("stats.title", 28.), 0., top),
+        (Localized::new("stats.summary", 16.)
+            .with_arg("days", stats.days)
+            .with_arg("cooked", cooked)
+            .with_arg("failures", stats.failures), 0., top - 34.),
+        (Localized::new("stats.dishes", 20.), -COLUMN, top - 84.),
+        (Localized::new("stats.customers", 20.), COLUMN, top - 84.)
+    ];
+
+    let mut dishes: Vec<(&String, &u32)> = stats.cooked.iter()
+        .filter(|(recipe, _)| !recipe.starts_with("failure") && recipe.as_str() != "none")
+        .collect();
+    dishes.sort_by_key(|(_, count)| Reverse(*count));
+    if dishes.is_empty() {
+        lines.push((Localized::new("stats.nothing", 16.), -COLUMN, row(0)));
+    }
+    for (index, (dish, count)) in dishes.into_iter().take(TOP_DISHES).enumerate() {
+        lines.push((Localized::new("stats.dish", 16.)
+            .with_arg("dish", locale.get(&format!("dish.{}", dish)))
+            .with_arg("cooked", count)
+            .with_arg("served", stats.served.get(dish).copied().unwrap_or(0)), -COLUMN, row(index)));
+    }
+
+    let mut customers: Vec<(&String, &Satisfaction)> = stats.customers.iter().collect();
+    customers.sort_by_key(|(_, satisfaction)| Reverse(satisfaction.served()));
+    if customers.is_empty() {
+        lines.push((Localized::new("stats.nothing", 16.), COLUMN, row(0)));
+    }
+    for (index, (customer, satisfaction)) in customers.into_iter().take(TOP_CUSTOMERS).enumerate() {
+        let name = roster.archetypes().iter()
+            .find(|archetype| &archetype.id == customer)
+            .map(|archetype| locale.get(&archetype.name))
+            .unwrap_or(customer.as_str());
+        lines.push((Localized::new("stats.customer", 16.)
+            .with_arg("name", name)
+            .with_arg("delighted", satisfaction.delighted)
+            .with_arg("disappointed", satisfaction.disappointed), COLUMN, row(index)));
+    }
+
+    lines.push((Localized::new("stats.time", 16.)
+        .with_arg("talking", clock(stats.seconds_in(&[GameState::Dialogue])))
+        .with_arg("cooking", clock(stats.seconds_in(&[GameState::Cooking])))
+        .with_arg("menus", clock(stats.seconds_in(&MENUS))), 0., -DESIGN_HEIGHT/2. + 60.));
+
+    for (line, x, y) in lines {
+        let (text, effects) = line.text(&locale, &fonts);
+        commands.spawn_bundle(Text2dBundle {
+            text: text.with_alignment(TextAlignment::CENTER),
+            ..default()
+        }).insert(StatsScene)
+            .insert(Anchored::center(x, y, 11.))
+            .insert(line)
+            .insert(effects);
+    }
+}
+
+pub fn stats_input(mut actions: ResMut<Actions>, mut game_state: ResMut<State<GameState>>) {
+    if actions.take(Action::Back) || actions.take(Action::Confirm) {
+        game_state.pop().ok();
+    }
+    actions.clear();
+}
+
+pub fn stats_cleanup(mut commands: Commands, entities: Query<(Entity, &StatsScene)>) {
+    for (entity, _) in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// Hours only show up once there are some.
+fn clock(seconds: f32) -> String {
+    let seconds = seconds as u32;
+    let (hours, minutes, seconds) = (seconds/3600, seconds/60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[derive(Component)]
+pub struct StatsScene;
@@ -0,0 +1,67 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use bevy::prelude::*;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+// Small RON files kept next to the game, one per kind of persisted data.
+// The browser build has no file system so it always starts fresh.
+#[cfg(not(target_arch = "wasm32"))]
+const DIRECTORY: &str = "save";
+
+// Set while replaying a recording, so playing it back leaves the player's files alone.
+#[cfg(not(target_arch = "wasm32"))]
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_read_only() {
+    READ_ONLY.store(true, Ordering::Relaxed);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path(name: &str) -> std::path::PathBuf {
+    std::path::Path::new(DIRECTORY).join(format!("{}.ron", name))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> T {
+    let contents = match std::fs::read_to_string(path(name)) {
+        Ok(contents) => contents,
+        Err(_) => return T::default()
+    };
+    match ron::de::from_str(&contents) {
+        Ok(value) => value,
+        Err(error) => {
+            warn!("Could not read {}: {}", name, error);
+            T::default()
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn store<T: Serialize>(name: &str, value: &T) {
+    if READ_ONLY.load(Ordering::Relaxed) {
+        return;
+    }
+    let contents = match ron::ser::to_string_pretty(value, default()) {
+        Ok(contents) => contents,
+        Err(error) => {
+            warn!("Could not serialize {}: {}", name, error);
+            return;
+        }
+    };
+    let result = std::fs::create_dir_all(DIRECTORY)
+        .and_then(|_| std::fs::write(path(name), contents));
+    if let Err(error) = result {
+        warn!("Could not write {}: {}", name, error);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load<T: DeserializeOwned + Default>(_name: &str) -> T {
+    T::default()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn store<T: Serialize>(_name: &str, _value: &T) { }
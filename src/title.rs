@@ -0,0 +1,108 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+use crate::actions::Actions;
+use crate::calendar::StorySchedule;
+use crate::common::*;
+use crate::customers::Roster;
+use crate::ending::Unlocks;
+use crate::layout::Anchored;
+use crate::locale::Locale;
+use crate::menu::{spawn_menu, Menu};
+use crate::reputation::Reputation;
+use crate::rng::Seed;
+use crate::save::{Progress, SaveGame};
+use crate::transition::Transition;
+
+const ENTRIES: [&str; 8] = ["title.new_game", "title.new_game_plus", "title.continue", "title.achievements"
+    , "title.stats", "title.mods", "title.settings", "title.quit"];
+
+pub fn title_setup(mut commands: Commands
+    , asset_server: Res<AssetServer>
+    , unlocks: Res<Unlocks>
+    , locale: Locale
+    , fonts: Res<Fonts>)
+{
+    let can_continue = SaveGame::load().is_some();
+    let entries = ENTRIES.map(|key| {
+        let enabled = match key {
+            "title.new_game_plus" => unlocks.new_game_plus(),
+            "title.continue" => can_continue,
+            _ => true
+        };
+        (key, enabled)
+    });
+    spawn_menu(&mut commands, TitleScene, 1., "title.name", &entries, &locale, &fonts);
+
+    commands.spawn_bundle(SpriteBundle {
+        texture: asset_server.load("backgrounds/dialogue.png"),
+        sprite: Sprite {
+            color: Color::rgb(0.5, 0.5, 0.5),
+            ..default()
+        },
+        ..default()
+    }).insert(TitleScene)
+        .insert(Anchored::center(0., 0., 0.).with_scale((0.71, 0.71, 0.).into()).covering());
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn title_input(mut actions: ResMut<Actions>
+    , mut menu: Query<&mut Menu, With<TitleScene>>
+    , mut progress: Progress
+    , seed: Res<Seed>
+    , unlocks: Res<Unlocks>
+    , roster: Roster
+    , schedule: StorySchedule
+    , time: Res<Time>
+    , mut game_state: ResMut<State<GameState>>
+    , mut transition: ResMut<Transition>
+    , mut exit: EventWriter<AppExit>)
+{
+    let picked = menu.get_single_mut().ok().and_then(|mut menu| menu.navigate(&mut actions));
+    actions.clear();
+    match picked.map(|index| ENTRIES[index]) {
+        // Nothing to start until the customers have loaded.
+        Some("title.new_game") if !roster.archetypes().is_empty() => {
+            let seed = seed.pick(&time);
+            info!("Starting a new game with seed {}", seed);
+            progress.start(seed, Reputation::default(), 0, roster.archetypes(), schedule.events());
+            transition.start(game_state.current(), GameState::Dialogue);
+        }
+        // New Game+ starts from what the town thought of the witch at the end of the last run.
+        Some("title.new_game_plus") if !roster.archetypes().is_empty() => {
+            let seed = seed.pick(&time);
+            info!("Starting New Game+ {} with seed {}", unlocks.cycles, seed);
+            progress.start(seed, unlocks.reputation.clone(), unlocks.cycles, roster.archetypes(), schedule.events());
+            transition.start(game_state.current(), GameState::Dialogue);
+        }
+        Some("title.continue") => {
+            if let Some(save) = SaveGame::load() {
+                progress.restore(save);
+                transition.start(game_state.current(), GameState::Dialogue);
+            }
+        }
+        Some("title.achievements") => {
+            game_state.push(GameState::Achievements).ok();
+        }
+        Some("title.stats") => {
+            game_state.push(GameState::Stats).ok();
+        }
+        Some("title.mods") => {
+            game_state.push(GameState::Mods).ok();
+        }
+        Some("title.settings") => {
+            game_state.push(GameState::Settings).ok();
+        }
+        Some("title.quit") => exit.send(AppExit),
+        _ => ()
+    }
+}
+
+pub fn title_cleanup(mut commands: Commands, entities: Query<(Entity, &TitleScene)>) {
+    for (entity, _) in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct TitleScene;
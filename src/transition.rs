@@ -0,0 +1,296 @@
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::text::Text2dBounds;
+use bevy::utils::BoxedFuture;
+use serde::Deserialize;
+
+use crate::common::*;
+use crate::layout::{Anchored, DESIGN_HEIGHT, DESIGN_WIDTH};
+
+// Above every scene and overlay.
+const OVERLAY_DEPTH: f32 = 50.;
+// How far in front of the new scene a dissolving copy of the old one is drawn.
+const DISSOLVE_DEPTH: f32 = 5.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum TransitionStyle {
+    Cut,
+    Fade,
+    Wipe,
+    CrossDissolve
+}
+
+// How a change of scene looks and how many seconds it takes. A missing `from`
+// or `to` matches any state.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TransitionRule {
+    pub from: Option<GameState>,
+    pub to: Option<GameState>,
+    pub style: TransitionStyle,
+    pub seconds: f32
+}
+
+impl TransitionRule {
+    fn matches(&self, from: &GameState, to: &GameState) -> bool {
+        self.from.iter().all(|state| state == from) && self.to.iter().all(|state| state == to)
+    }
+}
+
+// Every transition, loaded from `game.transitions.ron`. The first rule that
+// matches wins.
+#[derive(Debug, Clone, Default, TypeUuid)]
+#[uuid = "c4b1e7a2-93d5-4f08-8e6a-1d2f7b5c9e30"]
+pub struct Transitions(pub Vec<TransitionRule>);
+
+#[derive(Default)]
+pub struct TransitionsLoader;
+
+impl AssetLoader for TransitionsLoader {
+    fn load<'a>(&'a self
+        , bytes: &'a [u8]
+        , load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), anyhow::Error>>
+    {
+        Box::pin(async move {
+            let rules: Vec<TransitionRule> = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(Transitions(rules)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["transitions.ron"]
+    }
+}
+
+pub struct TransitionsHandle(pub Handle<Transitions>);
+
+impl TransitionsHandle {
+    pub fn load(asset_server: &AssetServer) -> Self {
+        TransitionsHandle(asset_server.load("game.transitions.ron"))
+    }
+}
+
+// Picks up the transitions once they load, and again whenever they change.
+pub fn load_transitions(mut loaded: EventReader<AssetEvent<Transitions>>
+    , handle: Res<TransitionsHandle>
+    , transitions: Res<Assets<Transitions>>
+    , mut transition: ResMut<Transition>)
+{
+    if loaded.iter().count() == 0 {
+        return;
+    }
+    if let Some(Transitions(rules)) = transitions.get(&handle.0) {
+        transition.rules = rules.clone();
+    }
+}
+
+type SceneQuery<'w, 's> = Query<'w, 's
+    , (&'static Anchored, &'static Transform, Option<&'static Sprite>, Option<&'static Handle<Image>>, Option<&'static Text>, Option<&'static Text2dBounds>)
+    , (Without<TransitionOverlay>, Without<Dissolving>)>;
+
+type DissolvingQuery<'w, 's> = Query<'w, 's
+    , (Entity, &'static Dissolving, Option<&'static mut Sprite>, Option<&'static mut Text>)
+    , Without<TransitionOverlay>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Idle,
+    Starting,
+    Covering,
+    Revealing
+}
+
+// A change of scene in progress. Scenes ask for the next state through `start`
+// and the state only changes once the old scene is hidden. Until the
+// transitions have loaded every change is a cut.
+pub struct Transition {
+    rules: Vec<TransitionRule>,
+    style: TransitionStyle,
+    phase: Phase,
+    timer: Timer,
+    next: Option<GameState>
+}
+
+impl Default for Transition {
+    fn default() -> Self {
+        Transition {
+            rules: Vec::new(),
+            style: TransitionStyle::Cut,
+            phase: Phase::Idle,
+            timer: Timer::from_seconds(0., false),
+            next: None
+        }
+    }
+}
+
+impl Transition {
+    pub fn start(&mut self, from: &GameState, to: GameState) {
+        if self.is_running() {
+            return;
+        }
+        let (style, seconds) = self.rules.iter()
+            .find(|rule| rule.matches(from, &to))
+            .map_or((TransitionStyle::Cut, 0.), |rule| (rule.style, rule.seconds));
+        // Fades and wipes spend half the time hiding the old scene and half showing the new one.
+        let seconds = match style {
+            TransitionStyle::Fade | TransitionStyle::Wipe => seconds/2.,
+            _ => seconds
+        };
+        self.style = style;
+        self.phase = Phase::Starting;
+        self.timer = Timer::from_seconds(seconds, false);
+        self.next = Some(to);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.phase != Phase::Idle
+    }
+
+    // How much the old scene still shows through: the overlay's opacity while
+    // fading, or the dissolving copy's.
+    fn coverage(&self) -> f32 {
+        match self.phase {
+            Phase::Idle | Phase::Starting => 0.,
+            Phase::Covering => self.timer.percent(),
+            Phase::Revealing => self.timer.percent_left()
+        }
+    }
+
+    fn switch(&mut self, game_state: &mut State<GameState>) {
+        if let Some(next) = self.next.take() {
+            game_state.replace(next).ok();
+        }
+    }
+}
+
+pub fn run_transition(mut commands: Commands
+    , mut transition: ResMut<Transition>
+    , mut game_state: ResMut<State<GameState>>
+    , scene: SceneQuery
+    , time: Res<Time>)
+{
+    match transition.phase {
+        Phase::Idle => (),
+        Phase::Starting => match transition.style {
+            TransitionStyle::Cut => {
+                transition.switch(&mut game_state);
+                transition.phase = Phase::Idle;
+            }
+            TransitionStyle::Fade | TransitionStyle::Wipe => {
+                let color = match transition.style {
+                    TransitionStyle::Wipe => Color::BLACK,
+                    _ => Color::rgba(0., 0., 0., 0.)
+                };
+                let offset = match transition.style {
+                    TransitionStyle::Wipe => -DESIGN_WIDTH,
+                    _ => 0.
+                };
+                commands.spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some((DESIGN_WIDTH, DESIGN_HEIGHT).into()),
+                        ..default()
+                    },
+                    ..default()
+                }).insert(TransitionOverlay)
+                    .insert(Anchored::center(offset, 0., OVERLAY_DEPTH).covering());
+                transition.phase = Phase::Covering;
+            }
+            TransitionStyle::CrossDissolve => {
+                snapshot(&mut commands, &scene);
+                transition.switch(&mut game_state);
+                transition.phase = Phase::Revealing;
+            }
+        },
+        Phase::Covering => {
+            if transition.timer.tick(time.delta()).finished() {
+                transition.switch(&mut game_state);
+                transition.timer.reset();
+                transition.phase = Phase::Revealing;
+            }
+        }
+        Phase::Revealing => {
+            if transition.timer.tick(time.delta()).finished() {
+                transition.phase = Phase::Idle;
+            }
+        }
+    }
+}
+
+// Copies what the old scene looks like so it can dissolve over the new one
+// once the old scene's own cleanup has removed it.
+fn snapshot(commands: &mut Commands
+    , scene: &SceneQuery)
+{
+    for (anchored, transform, sprite, image, text, bounds) in scene.iter() {
+        let mut place = *anchored;
+        place.offset.z += DISSOLVE_DEPTH;
+        let mut transform = *transform;
+        transform.translation.z += DISSOLVE_DEPTH;
+        if let Some(sprite) = sprite {
+            commands.spawn_bundle(SpriteBundle {
+                sprite: sprite.clone(),
+                texture: image.cloned().unwrap_or_default(),
+                transform,
+                ..default()
+            }).insert(place)
+                .insert(Dissolving(vec![sprite.color.a()]));
+        }
+        if let Some(text) = text {
+            commands.spawn_bundle(Text2dBundle {
+                text: text.clone(),
+                text_2d_bounds: bounds.cloned().unwrap_or_default(),
+                transform,
+                ..default()
+            }).insert(place)
+                .insert(Dissolving(text.sections.iter().map(|section| section.style.color.a()).collect()));
+        }
+    }
+}
+
+pub fn transition_visuals(mut commands: Commands
+    , transition: Res<Transition>
+    , mut overlay: Query<(Entity, &mut Sprite, &mut Anchored), With<TransitionOverlay>>
+    , mut dissolving: DissolvingQuery)
+{
+    let coverage = transition.coverage();
+    for (entity, mut sprite, mut place) in overlay.iter_mut() {
+        if !transition.is_running() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        match transition.style {
+            // Slides in from the left and carries on out to the right.
+            TransitionStyle::Wipe => {
+                let side = if transition.phase == Phase::Revealing { 1. } else { -1. };
+                place.offset.x = side*(1. - coverage)*DESIGN_WIDTH;
+            }
+            _ => {
+                sprite.color.set_a(coverage);
+            }
+        }
+    }
+
+    for (entity, Dissolving(alphas), sprite, text) in dissolving.iter_mut() {
+        if !transition.is_running() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        if let Some(mut sprite) = sprite {
+            sprite.color.set_a(alphas[0]*coverage);
+        }
+        if let Some(mut text) = text {
+            for (section, alpha) in text.sections.iter_mut().zip(alphas) {
+                section.style.color.set_a(alpha*coverage);
+            }
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct TransitionOverlay;
+
+// A copy of part of the previous scene, fading out, with the opacity it started at.
+#[derive(Component)]
+pub struct Dissolving(Vec<f32>);